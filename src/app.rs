@@ -1,27 +1,48 @@
 //! Contains app-related things (so just about everything)
+pub mod cat;
+pub mod command;
+pub mod effects;
+pub mod fonts;
 pub mod helpers;
-mod keystroke;
+pub mod idle;
+pub mod keystroke;
+pub mod layout;
+mod viewports;
 
-use crate::app::helpers::{load_color_image_from_path, load_texture_from_color_image};
+use crate::app::cat::{self, CatView, ExtraCat, Scene};
+use crate::app::command::{AppCommand, CommandQueue};
 use crate::app::keystroke::KeystrokeState;
+#[cfg(feature = "audio")]
+use crate::audio;
+use crate::config::{Config, OverFullscreen};
 use crate::consts::graphics::*;
 use crate::consts::keyboard::*;
-use crate::theme::{AppThemeImage, AppThemeTexture, ThemeSet};
+#[cfg(target_os = "linux")]
+use crate::devices;
+use crate::fullscreen::{self, FullscreenWindow};
+use crate::hotkeys::Hotkey;
+#[cfg(unix)]
+use crate::ipc;
+use crate::placement;
+use crate::theme::{self, AppTheme, ThemeSet};
+#[cfg(feature = "tray")]
+use crate::tray::{self, Tray};
+#[cfg(all(target_os = "linux", feature = "dbus"))]
+use crate::{dbus, lock};
+use crate::{focus, http, metrics, overlay};
 use dashmap::DashMap;
-use eframe::epaint::{
-    Pos2, TextShape,
-    text::{LayoutJob, TextFormat},
-};
-use egui::{
-    Align, Color32, Context, FontFamily, FontId, LayerId, Rect, Stroke, TextureId, ViewportCommand,
-    text::LayoutSection,
-};
+use display_info::DisplayInfo;
+use eframe::epaint::Pos2;
+use egui::{Color32, Context, LayerId, Stroke, StrokeKind, ViewportCommand};
 use inputbot::KeybdKey;
+use parking_lot::RwLock;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::time::Instant;
 use std::{
-    cell::UnsafeCell,
     default::Default,
-    ops::Deref,
     sync::{
         Arc, OnceLock,
         atomic::{AtomicBool, Ordering},
@@ -35,7 +56,7 @@ use std::{
 pub struct BongoApp {
     /// Theme (bongocat images) to be used
     #[serde(skip)]
-    themes: Arc<UnsafeCell<ThemeSet>>,
+    themes: Arc<RwLock<ThemeSet>>,
     /// Access to the eGui `Context` from other threads
     #[serde(skip)]
     context_access: OnceLock<Context>,
@@ -45,15 +66,74 @@ pub struct BongoApp {
     /// Notifies threads of exiting
     #[serde(skip)]
     exit_notify: Arc<AtomicBool>,
-    /// Are they rendered yet?
+    /// Key shapes and animations of the main cat
+    #[serde(skip)]
+    cat: CatView,
+    /// Cats in windows of their own
+    #[serde(skip)]
+    cats: Vec<ExtraCat>,
+    /// Font files registered with egui
+    #[serde(skip)]
+    fonts: HashSet<PathBuf>,
+    /// User configuration
+    #[serde(skip)]
+    config: Config,
+    /// Commands sent from other threads
+    #[serde(skip)]
+    commands: CommandQueue,
+    /// Window state remembered between runs
+    window: WindowState,
+    /// Whether the window can be clicked and dragged around
+    #[serde(skip)]
+    edit_mode: bool,
+    /// Whether the cat is hidden
+    #[serde(skip)]
+    hidden: bool,
+    /// Id of the theme in use
+    theme: Arc<RwLock<String>>,
+    /// Whether the stats window is open
     #[serde(skip)]
-    themes_rendered: bool,
-    /// Holds shapes we don't want to keep redrawing
+    stats_open: bool,
+    /// Whether the settings window is open
     #[serde(skip)]
-    shape_holder: Vec<(&'static VisualKeybdKeyHolder, Vec<Pos2>)>,
+    settings_open: bool,
+    /// Where the focused fullscreen window is, if there is one
+    #[serde(skip)]
+    fullscreen: FullscreenWindow,
+    /// Whether `window.over_fullscreen = "hide"` is hiding the cat this frame
+    #[serde(skip)]
+    hidden_over_fullscreen: bool,
+    /// Sound effect volume and mute
+    #[cfg(feature = "audio")]
+    #[serde(skip)]
+    audio: Arc<audio::Controls>,
+    /// Keeps the tray icon alive
+    #[cfg(feature = "tray")]
+    #[serde(skip)]
+    tray: Option<Tray>,
+    /// Where the control socket is listening
+    #[cfg(unix)]
+    #[serde(skip)]
+    ipc_socket: Option<PathBuf>,
+    /// Where HTTP servers are listening
+    #[serde(skip)]
+    http_servers: Vec<SocketAddr>,
     /// Keystroke-related state
     keystroke_state: Arc<KeystrokeState>,
 }
+
+/// Window state remembered between runs
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct WindowState {
+    /// Scale factor set at runtime, overrides the configured one until that changes
+    pub scale: Option<f32>,
+    /// Configured scale factor when `scale` was set
+    pub configured_scale: Option<f32>,
+    /// Where the window was moved to, if away from its configured corner
+    pub position: Option<Pos2>,
+}
+
 impl BongoApp {
     pub fn log_key(&self, key: &KeybdKey) {
         self.keystroke_state.log_keystroke(key);
@@ -64,7 +144,7 @@ impl BongoApp {
     /// # Panics
     /// When context instance is somehow deserialized
     #[must_use]
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, config: Config) -> Self {
         let mut this: Self;
 
         egui_extras::install_image_loaders(&cc.egui_ctx);
@@ -75,6 +155,18 @@ impl BongoApp {
             this = Self::default();
         }
 
+        this.config = config;
+        if this.window.configured_scale != Some(this.config.window.scale) {
+            this.window.scale = None;
+        }
+        #[cfg(feature = "audio")]
+        {
+            this.audio = Arc::new(audio::Controls::new(&this.config.audio));
+        }
+        Arc::get_mut(&mut this.keystroke_state)
+            .expect("Keystroke state isn't shared yet")
+            .set_privacy(this.config.privacy.mode);
+
         assert!(
             this.context_access.set(cc.egui_ctx.clone()).is_ok(),
             "Context instance was already set?"
@@ -88,41 +180,213 @@ impl BongoApp {
             context_access: this.context_access.clone(),
             handles: this.handles.clone(),
             exit_notify: this.exit_notify.clone(),
-            themes_rendered: false,
-            shape_holder: this.shape_holder.clone(),
+            cat: CatView::default(),
+            cats: vec![],
+            fonts: HashSet::new(),
+            config: this.config.clone(),
+            commands: this.commands.clone(),
+            window: this.window.clone(),
+            edit_mode: false,
+            hidden: false,
+            theme: this.theme.clone(),
+            stats_open: false,
+            settings_open: false,
+            fullscreen: this.fullscreen.clone(),
+            hidden_over_fullscreen: false,
+            #[cfg(feature = "audio")]
+            audio: this.audio.clone(),
+            #[cfg(feature = "tray")]
+            tray: None,
+            #[cfg(unix)]
+            ipc_socket: None,
+            http_servers: vec![],
             keystroke_state: this.keystroke_state.clone(),
         });
 
-        let arc_clone = arc_this.themes.clone();
-        let theme_set = unsafe { arc_clone.deref().as_mut_unchecked() };
+        let themes = this
+            .config
+            .themes_dir()
+            .map(AppTheme::discover)
+            .unwrap_or_default();
+        for handle in theme::load_in_background(
+            &this.themes,
+            &themes,
+            &cc.egui_ctx,
+            &this.commands.sender(),
+            &this.exit_notify,
+        ) {
+            this.insert_handle_autoincrement(handle);
+        }
+
+        this.fonts = fonts::register(
+            &cc.egui_ctx,
+            this.config
+                .counter
+                .font
+                .iter()
+                .chain(
+                    themes
+                        .iter()
+                        .filter_map(|theme| theme.manifest.counter.font.as_ref()),
+                )
+                .map(PathBuf::as_path),
+        );
+
+        let theme_ids = this.themes.read().theme_ids();
+        if !theme_ids.iter().any(|id| **id == *this.theme.read())
+            && let Some(id) = theme_ids.first()
+        {
+            *this.theme.write() = id.to_string();
+        }
 
-        for theme in &theme_set.themes {
-            theme_set.themes_loaded.push(AppThemeImage {
-                id: theme.id.clone(),
-                paws_both: load_color_image_from_path(&theme.paws_both),
-                paws_left: load_color_image_from_path(&theme.paws_left),
-                paws_right: load_color_image_from_path(&theme.paws_right),
-                paws_up: load_color_image_from_path(&theme.paws_up),
-            });
+        #[cfg(feature = "tray")]
+        if this.config.tray.enabled {
+            this.tray = tray::create(theme_ids, this.commands.sender(), cc.egui_ctx.clone());
         }
 
-        for theme in &theme_set.themes_loaded {
-            theme_set.themes_rendered.push(AppThemeTexture {
-                id: theme.id.clone(),
-                paws_both: None,
-                paws_left: None,
-                paws_right: None,
-                paws_up: None,
-            });
+        #[cfg(unix)]
+        if this.config.ipc.enabled {
+            let path = ipc::socket_path(&this.config.ipc);
+            let handles = ipc::Handles {
+                keystroke_state: this.keystroke_state.clone(),
+                theme: this.theme.clone(),
+                themes: this.themes.clone(),
+                commands: this.commands.sender(),
+                context: cc.egui_ctx.clone(),
+            };
+            match ipc::serve(path.clone(), handles, this.exit_notify.clone()) {
+                Ok(handle) => {
+                    this.insert_handle_autoincrement(handle);
+                    this.ipc_socket = Some(path);
+                }
+                Err(e) => log::warn!("Could not open control socket {}: {e}", path.display()),
+            }
         }
 
+        #[cfg(all(target_os = "linux", feature = "dbus"))]
+        if this.config.dbus.enabled {
+            let handles = ipc::Handles {
+                keystroke_state: this.keystroke_state.clone(),
+                theme: this.theme.clone(),
+                themes: this.themes.clone(),
+                commands: this.commands.sender(),
+                context: cc.egui_ctx.clone(),
+            };
+            match dbus::serve(&this.config.dbus, handles, this.exit_notify.clone()) {
+                Ok(handle) => this.insert_handle_autoincrement(handle),
+                Err(e) => log::warn!("Could not publish on the session bus: {e}"),
+            }
+        }
+
+        if this.config.overlay.enabled {
+            match overlay::serve(
+                &this.config.overlay,
+                &themes,
+                this.keystroke_state.clone(),
+                this.theme.clone(),
+                this.exit_notify.clone(),
+            ) {
+                Ok((address, handle)) => {
+                    log::info!("Overlay available at http://{address}/");
+                    this.insert_handle_autoincrement(handle);
+                    this.http_servers.push(address);
+                }
+                Err(e) => log::warn!(
+                    "Could not start overlay server on {}: {e}",
+                    this.config.overlay.address
+                ),
+            }
+        }
+
+        #[cfg(feature = "audio")]
+        if this.config.audio.enabled {
+            let handle = audio::play(
+                &themes,
+                this.audio.clone(),
+                this.keystroke_state.clone(),
+                this.theme.clone(),
+                this.exit_notify.clone(),
+            );
+            this.insert_handle_autoincrement(handle);
+        }
+
+        if this.config.metrics.enabled {
+            match metrics::serve(
+                &this.config.metrics,
+                this.keystroke_state.clone(),
+                this.exit_notify.clone(),
+            ) {
+                Ok((address, handle)) => {
+                    log::info!("Metrics available at http://{address}/metrics");
+                    this.insert_handle_autoincrement(handle);
+                    this.http_servers.push(address);
+                }
+                Err(e) => log::warn!(
+                    "Could not start metrics server on {}: {e}",
+                    this.config.metrics.address
+                ),
+            }
+        }
+
+        let needs_fullscreen = std::iter::once(&this.config.window)
+            .chain(this.config.cats.iter().map(|cat| &cat.window))
+            .any(|window| window.over_fullscreen != OverFullscreen::Show);
+        if needs_fullscreen
+            && let Some(handle) = fullscreen::watch(
+                cc.egui_ctx.clone(),
+                this.fullscreen.clone(),
+                this.exit_notify.clone(),
+            )
+        {
+            this.insert_handle_autoincrement(handle);
+        }
+
+        if let Some(handle) = focus::watch(
+            &this.config.privacy,
+            this.keystroke_state.clone(),
+            this.exit_notify.clone(),
+        ) {
+            this.insert_handle_autoincrement(handle);
+        }
+
+        #[cfg(all(target_os = "linux", not(feature = "dbus")))]
+        if this.config.privacy.pause_on_lock {
+            log::warn!(
+                "Built without the `dbus` feature, so locking the session won't pause counting"
+            );
+        }
+
+        #[cfg(all(target_os = "linux", feature = "dbus"))]
+        if this.config.privacy.pause_on_lock
+            && let Some(handle) =
+                lock::watch(this.keystroke_state.clone(), this.exit_notify.clone())
+        {
+            this.insert_handle_autoincrement(handle);
+        }
+
+        let hotkeys = Arc::new(arc_this.config.hotkeys.bindings());
+
         for key in &KEYS {
             for key in *key {
                 let keystroke_state = arc_this.keystroke_state.clone();
                 let context_access = arc_this.context_access.clone();
+                let commands = arc_this.commands.sender();
+                let hotkeys = hotkeys.clone();
                 let key_clone = key.key;
                 key.key.bind(move || {
-                    keystroke_state.log_keystroke(&key_clone);
+                    // The key finishing a hotkey is a control, not typing, so it is neither counted nor lit.
+                    // Its modifiers were already counted when they went down.
+                    let mut is_hotkey = false;
+                    for (_, command) in hotkeys
+                        .iter()
+                        .filter(|(hotkey, _)| hotkey.matches(key_clone))
+                    {
+                        let _ = commands.send(command.clone());
+                        is_hotkey = true;
+                    }
+                    if !is_hotkey {
+                        keystroke_state.log_keystroke(&key_clone);
+                    }
                     context_access.wait().request_repaint();
                 });
             }
@@ -132,102 +396,210 @@ impl BongoApp {
             inputbot::handle_input_events(true);
         }));
 
-        let arc_clone = arc_this.clone();
-        let exit_notify = arc_clone.exit_notify.clone();
-        let context = arc_clone.context_access.wait().clone();
-        let _ = this.keystroke_state.input_update_thread.lock().insert(
-            arc_this.insert_handle_autoincrement(thread::spawn(move || {
-                loop {
-                    if exit_notify.load(Ordering::Relaxed) {
-                        return;
-                    }
-                    thread::park();
-                    context.request_repaint();
-                }
-            })),
+        let displays = DisplayInfo::all().unwrap_or_default();
+        this.cats = this
+            .config
+            .cats
+            .iter()
+            .enumerate()
+            .map(|(i, config)| {
+                let keystroke_state = match &config.device {
+                    Some(device) => this.device_keystroke_state(device, &hotkeys),
+                    None => this.keystroke_state.clone(),
+                };
+                ExtraCat::new(i, config.clone(), keystroke_state, &displays)
+            })
+            .collect();
+
+        let mut states = vec![this.keystroke_state.clone()];
+        states.extend(
+            this.cats
+                .iter()
+                .map(|cat| cat.keystroke_state.clone())
+                .filter(|state| !Arc::ptr_eq(state, &this.keystroke_state)),
         );
-
-        let arc_clone = arc_this.clone();
-        let state = arc_clone.keystroke_state.clone();
-        let exit_notify = arc_clone.exit_notify.clone();
-        arc_this.insert_handle_autoincrement(thread::spawn(move || {
-            loop {
-                if exit_notify.load(Ordering::Relaxed) {
-                    return;
+        let exit_notify = this.exit_notify.clone();
+        let context = cc.egui_ctx.clone();
+        let handle = thread::spawn({
+            let states = states.clone();
+            move || {
+                // Sleeps until a key lights up or the next lit key goes out, so idle costs no wakeups
+                while !exit_notify.load(Ordering::Relaxed) {
+                    context.request_repaint();
+                    let next = states
+                        .iter()
+                        .filter_map(|state| state.cleanup_outdated(KEY_PRESSED_LIT_DELAY))
+                        .min();
+                    match next {
+                        Some(deadline) => {
+                            thread::park_timeout(deadline.saturating_duration_since(Instant::now()))
+                        }
+                        None => thread::park(),
+                    }
                 }
-                thread::sleep(KEY_PRESSED_CLEANUP_DELAY);
-                state.cleanup_outdated(KEY_PRESSED_LIT_DELAY);
             }
-        }));
+        });
+        let thread = this.insert_handle_autoincrement(handle);
+        for state in &states {
+            *state.input_update_thread.lock() = Some(thread.clone());
+        }
 
-        let mut rects: Vec<(&VisualKeybdKeyHolder, Rect)> = vec![];
+        this.apply_window_size(&cc.egui_ctx);
+        this.place_window(&cc.egui_ctx);
 
-        fn rotate_point(center: Pos2, p: Pos2, theta: f32) -> Pos2 {
-            let x = p.x - center.x;
-            let y = p.y - center.y;
-            let cos_theta = theta.cos();
-            let sin_theta = theta.sin();
-            Pos2::new(
-                center.x + (x * cos_theta - y * sin_theta),
-                center.y + (x * sin_theta + y * cos_theta),
-            )
+        this
+    }
+
+    /// Keystrokes of a cat that only reacts to keyboards named like `device`, paused along with the main cat
+    /// and not counting `hotkeys`
+    #[cfg(target_os = "linux")]
+    fn device_keystroke_state(
+        &self,
+        device: &str,
+        hotkeys: &Arc<Vec<(Hotkey, AppCommand)>>,
+    ) -> Arc<KeystrokeState> {
+        let mut state = KeystrokeState::default();
+        state.set_privacy(self.config.privacy.mode);
+        let state = Arc::new(state);
+
+        if devices::capture(
+            device,
+            state.clone(),
+            self.keystroke_state.clone(),
+            hotkeys.clone(),
+        ) == 0
+        {
+            log::warn!("No readable keyboard is named like \"{device}\", so its cat won't react");
         }
+        state
+    }
 
-        fn rotate_rect(rect: Rect, center: Pos2, theta: f32) -> [Pos2; 4] {
-            let corners = [
-                rect.left_top(),
-                rect.right_top(),
-                rect.right_bottom(),
-                rect.left_bottom(),
-            ];
-            corners.map(|p| rotate_point(center, p, theta))
-        }
-
-        let mut row_off = 0;
-        for y in (0..KEYS.len()).rev() {
-            let mut col_off = 0;
-            for x in (0..KEYS[y].len()).rev() {
-                let key = &KEYS[y][x];
-                let size_offset = key.size * 10.0;
-
-                rects.push((
-                    key,
-                    Rect::from_min_max(
-                        Pos2::new(
-                            (4 + col_off) as f32,
-                            ((WINDOW_HEIGHT as u64 - 95) + row_off) as f32,
-                        ),
-                        Pos2::new(
-                            4.0 + size_offset + col_off as f32,
-                            ((WINDOW_HEIGHT as u64 - 95 + PADDING_PIXELS) + row_off) as f32,
-                        ),
-                    ),
-                ));
-
-                col_off += (PADDING_PIXELS as f32 * key.size) as u64;
-            }
-            row_off += PADDING_PIXELS;
+    #[cfg(not(target_os = "linux"))]
+    fn device_keystroke_state(
+        &self,
+        _device: &str,
+        _hotkeys: &Arc<Vec<(Hotkey, AppCommand)>>,
+    ) -> Arc<KeystrokeState> {
+        log::warn!("Cats can only pick a keyboard on Linux, so this one reacts to every keyboard");
+        self.keystroke_state.clone()
+    }
+
+    /// The scale factor currently in use
+    #[must_use]
+    pub fn scale(&self) -> f32 {
+        self.window
+            .scale
+            .unwrap_or(self.config.window.scale)
+            .clamp(MIN_SCALE, MAX_SCALE)
+    }
+
+    /// Changes the scale factor, resizing the window and everything drawn in it
+    pub fn set_scale(&mut self, ctx: &Context, scale: f32) {
+        self.window.scale = Some(scale.clamp(MIN_SCALE, MAX_SCALE));
+        self.window.configured_scale = Some(self.config.window.scale);
+        self.apply_window_size(ctx);
+        if self.window.position.is_none() {
+            // Stay anchored to the configured corner
+            self.place_window(ctx);
         }
+    }
 
-        // Calculate the keyboard center (e.g., average of key rect centers)
-        let all_rects: Vec<Rect> = rects.iter().map(|(_, r)| *r).collect();
-        let sum = all_rects.iter().fold(Pos2::ZERO, |s, r| {
-            let c = r.center();
-            Pos2::new(s.x + c.x, s.y + c.y)
-        });
-        let count = (all_rects.len() - 1) as f32;
-        let keyboard_center = if count > 0.0 {
-            Pos2::new(sum.x / count, sum.y / count)
+    fn apply_window_size(&self, ctx: &Context) {
+        let size = layout::window_size(self.scale());
+        ctx.send_viewport_cmd(ViewportCommand::MinInnerSize(size));
+        ctx.send_viewport_cmd(ViewportCommand::MaxInnerSize(size));
+        ctx.send_viewport_cmd(ViewportCommand::InnerSize(size));
+    }
+
+    fn place_window(&self, ctx: &Context) {
+        let displays = DisplayInfo::all().unwrap_or_default();
+        if let Some(position) = placement::window_position(
+            &self.config.window,
+            &displays,
+            layout::window_size(self.scale()),
+            self.window.position,
+        ) {
+            ctx.send_viewport_cmd(ViewportCommand::OuterPosition(position));
+        }
+    }
+
+    /// Remembers where the window is, unless that's just its configured corner
+    fn record_position(&mut self, ctx: &Context) {
+        let Some(position) = ctx.input(|i| i.viewport().outer_rect).map(|r| r.min) else {
+            return;
+        };
+
+        let displays = DisplayInfo::all().unwrap_or_default();
+        let configured = placement::window_position(
+            &self.config.window,
+            &displays,
+            layout::window_size(self.scale()),
+            None,
+        );
+
+        self.window.position = if configured.is_some_and(|c| c.distance(position) < 1.0) {
+            None
         } else {
-            Pos2::ZERO
+            Some(position)
         };
+    }
+
+    /// Switches to theme `id`, telling subscribers
+    fn set_theme(&self, id: String) {
+        *self.theme.write() = id.clone();
+        self.keystroke_state.announce_theme(&id);
+    }
 
-        for (key, rect) in rects {
-            this.shape_holder
-                .push((key, rotate_rect(rect, keyboard_center, CAT_ANGLE).to_vec()));
+    fn handle_command(&mut self, ctx: &Context, command: AppCommand) {
+        match command {
+            AppCommand::SetScale(scale) => self.set_scale(ctx, scale),
+            AppCommand::ScaleUp => self.set_scale(ctx, self.scale() + SCALE_STEP),
+            AppCommand::ScaleDown => self.set_scale(ctx, self.scale() - SCALE_STEP),
+            AppCommand::ToggleEditMode => self.set_edit_mode(ctx, !self.edit_mode),
+            AppCommand::ToggleVisibility => self.hidden = !self.hidden,
+            AppCommand::CycleTheme => {
+                let next = self.themes.read().next_theme_id(&self.theme.read());
+                if let Some(id) = next {
+                    self.set_theme(id.to_string());
+                }
+            }
+            AppCommand::ResetSession => self.keystroke_state.reset_session(),
+            AppCommand::TogglePause => self.keystroke_state.toggle_paused(),
+            AppCommand::ToggleMute => {
+                #[cfg(feature = "audio")]
+                self.audio.toggle_muted();
+            }
+            AppCommand::CycleBackground => {
+                self.config.window.background = self.config.window.background.next();
+            }
+            AppCommand::SetBackground(background) => self.config.window.background = background,
+            AppCommand::SetTheme(id) => self.set_theme(id),
+            AppCommand::ForgetTheme(id) => {
+                #[cfg(feature = "tray")]
+                if let Some(tray) = &self.tray {
+                    tray.remove_theme(&id);
+                }
+                let current = self.theme.read().clone();
+                let first = self.themes.read().theme_ids().first().cloned();
+                if current == id
+                    && let Some(first) = first
+                {
+                    self.set_theme(first.to_string());
+                }
+            }
+            AppCommand::OpenStats => self.stats_open = true,
+            AppCommand::OpenSettings => self.settings_open = true,
+            AppCommand::Quit => ctx.send_viewport_cmd(ViewportCommand::Close),
         }
+    }
 
-        this
+    /// Edit mode stops the window from being click-through so it can be dragged around
+    pub fn set_edit_mode(&mut self, ctx: &Context, edit_mode: bool) {
+        if self.edit_mode && !edit_mode {
+            self.record_position(ctx);
+        }
+        self.edit_mode = edit_mode;
+        ctx.send_viewport_cmd(ViewportCommand::MousePassthrough(!edit_mode));
     }
 
     pub fn insert_handle_autoincrement(&self, handle: JoinHandle<()>) -> Thread {
@@ -259,162 +631,82 @@ impl BongoApp {
 
 impl eframe::App for BongoApp {
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
-        let themes = unsafe { self.themes.as_mut_unchecked() };
-        if !self.themes_rendered {
-            for theme in themes.themes_rendered.iter_mut() {
-                let app_theme = themes
-                    .themes_loaded
-                    .iter()
-                    .find(|t| Arc::ptr_eq(&t.id, &theme.id))
-                    .unwrap();
-                theme.paws_both.get_or_insert(load_texture_from_color_image(
-                    ctx,
-                    &app_theme.paws_both,
-                    format!("paws_both_{}", &app_theme.id),
-                ));
-                theme.paws_left.get_or_insert(load_texture_from_color_image(
-                    ctx,
-                    &app_theme.paws_left,
-                    format!("paws_left_{}", &app_theme.id),
-                ));
-                theme
-                    .paws_right
-                    .get_or_insert(load_texture_from_color_image(
-                        ctx,
-                        &app_theme.paws_right,
-                        format!("paws_right_{}", &app_theme.id),
-                    ));
-                theme.paws_up.get_or_insert(load_texture_from_color_image(
-                    ctx,
-                    &app_theme.paws_up,
-                    format!("paws_up_{}", &app_theme.id),
-                ));
-            }
-            self.themes_rendered = true;
+        for command in self.commands.drain() {
+            self.handle_command(ctx, command);
         }
 
-        let painter = ctx.layer_painter(LayerId::background());
+        self.show_stats(ctx);
+        self.show_settings(ctx);
 
-        let mut left_side_down = false;
-        let mut right_side_down = false;
-        let mut is_o_face = false;
+        let scale = self.scale();
+        if self.themes.read().needs_upload() {
+            self.themes.write().upload(ctx);
+        }
 
-        for (key, rect) in self.shape_holder.iter() {
-            let color = if let KeybdKey::OtherKey(code) = key.key {
-                if code == u64::MAX - 1 {
-                    Color32::TRANSPARENT
-                } else {
-                    Color32::WHITE
-                }
-            } else {
-                Color32::WHITE
-            };
+        let fullscreen = *self.fullscreen.lock();
+        self.hidden_over_fullscreen = self.config.window.over_fullscreen == OverFullscreen::Hide
+            && cat::covers_fullscreen(ctx, fullscreen);
 
-            painter.add(egui::Shape::convex_polygon(
-                rect.clone(),
-                match key.key {
-                    KeybdKey::OtherKey(v) => {
-                        if v < u64::MAX - 2
-                            && self.keystroke_state.lit_keys_map[key.key].load(Ordering::Relaxed)
-                        {
-                            if key.column < 10 {
-                                left_side_down = true;
-                            } else {
-                                right_side_down = true;
-                            }
-                            Color32::LIGHT_BLUE
-                        } else {
-                            Color32::TRANSPARENT
-                        }
-                    }
-                    _ => {
-                        if self.keystroke_state.lit_keys_map[key.key].load(Ordering::Relaxed) {
-                            if key.column < 8 {
-                                left_side_down = true;
-                            } else {
-                                right_side_down = true;
-                            }
-                            if !is_o_face {
-                                is_o_face = matches!(
-                                    key.key,
-                                    KeybdKey::OKey | KeybdKey::Numrow0Key | KeybdKey::Numpad0Key
-                                );
-                            }
-                            Color32::LIGHT_BLUE
-                        } else {
-                            Color32::TRANSPARENT
-                        }
-                    }
-                },
-                Stroke::new(1.0, color),
-            ));
+        if self.hidden {
+            return;
         }
 
-        let text = format!(
-            "{}",
-            self.keystroke_state.keystrokes.load(Ordering::Relaxed)
-        );
-
-        painter.add(
-            TextShape::new(
-                Pos2::new(
-                    WINDOW_WIDTH - WINDOW_WIDTH / 2.0,
-                    WINDOW_HEIGHT - (WINDOW_HEIGHT / 12.5) * 2.25,
-                ),
-                painter.layout_job(LayoutJob {
-                    sections: vec![LayoutSection {
-                        leading_space: 0.0,
-                        byte_range: 0usize..text.len(),
-                        format: TextFormat::simple(
-                            FontId::new(WINDOW_HEIGHT / 12.5, FontFamily::Proportional),
-                            Color32::WHITE,
-                        ),
-                    }],
-                    text,
-                    halign: Align::Max,
-                    ..Default::default()
-                }),
-                Color32::PLACEHOLDER,
-            )
-            .with_angle(CAT_ANGLE),
+        let themes = self.themes.read();
+        let theme = self.theme.read().clone();
+        self.cat.paint(
+            ctx,
+            &Scene {
+                config: &self.config,
+                window: &self.config.window,
+                keystroke_state: &self.keystroke_state,
+                themes: &themes,
+                theme: &theme,
+                fonts: &self.fonts,
+                fullscreen,
+                scale,
+            },
         );
+        for cat in &mut self.cats {
+            cat.show(ctx, &self.config, &themes, &theme, &self.fonts, fullscreen);
+        }
 
-        let theme = if is_o_face {
-            themes
-                .themes_rendered
-                .get(1)
-                .or_else(|| themes.themes_rendered.first())
-        } else {
-            themes.themes_rendered.first()
-        };
-
-        if let Some(theme) = theme {
-            let id = if left_side_down
-                && right_side_down
-                && let Some(tex) = &theme.paws_both
-            {
-                tex.id()
-            } else if left_side_down && let Some(tex) = &theme.paws_left {
-                tex.id()
-            } else if right_side_down && let Some(tex) = &theme.paws_right {
-                tex.id()
-            } else if let Some(tex) = &theme.paws_up {
-                tex.id()
-            } else {
-                TextureId::default()
-            };
+        if self.edit_mode {
+            if ctx.input(|i| i.pointer.primary_pressed()) {
+                ctx.send_viewport_cmd(ViewportCommand::StartDrag);
+            }
 
-            painter.image(id, WINDOW_RECT, UV_RECT, Color32::WHITE);
+            ctx.layer_painter(LayerId::background()).rect_stroke(
+                layout::window_rect(scale).shrink(1.0),
+                4.0 * scale,
+                Stroke::new(1.0, Color32::from_white_alpha(96)),
+                StrokeKind::Inside,
+            );
         }
     }
 
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        if self.config.window.remember_position
+            && let Some(ctx) = self.context_access.get().cloned()
+        {
+            self.record_position(&ctx);
+        }
         eframe::set_value(storage, eframe::APP_KEY, self);
     }
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
         self.exit_notify.store(true, Ordering::Relaxed);
+        #[cfg(unix)]
+        if let Some(path) = &self.ipc_socket {
+            ipc::wake(path);
+        }
+        for address in &self.http_servers {
+            http::wake(*address);
+        }
         inputbot::stop_handling_input_events();
+        #[cfg(feature = "tray")]
+        if let Some(tray) = self.tray.take() {
+            tray.quit();
+        }
         self.handles.alter_all(|_, h| {
             if let Some(handle) = h {
                 handle.thread().unpark();
@@ -426,6 +718,10 @@ impl eframe::App for BongoApp {
     }
 
     fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
-        egui::Rgba::TRANSPARENT.to_array()
+        if self.hidden || self.hidden_over_fullscreen {
+            return egui::Rgba::TRANSPARENT.to_array();
+        }
+        let window = &self.config.window;
+        egui::Rgba::from(window.background.clear_color(window.background_color.0)).to_array()
     }
 }
@@ -0,0 +1,338 @@
+//! Drawing one cat: the main window's, or another one in a window of its own
+use crate::app::effects::Effects;
+use crate::app::fonts;
+use crate::app::idle::{self, IdleAnimation, ZZZ_STEP};
+use crate::app::keystroke::KeystrokeState;
+use crate::app::layout::{self, ShapeHolder, ShapeParams};
+use crate::config::{APP_ID, Background, CatConfig, Config, OverFullscreen, WindowConfig};
+use crate::consts::graphics::*;
+use crate::placement;
+use crate::theme::{ThemeManifest, ThemeSet};
+use display_info::DisplayInfo;
+use eframe::epaint::{
+    Pos2, Rect, TextShape, Vec2,
+    text::{LayoutJob, LayoutSection, TextFormat},
+};
+use egui::{
+    Align2, Color32, Context, FontFamily, FontId, LayerId, Stroke, TextureId, ViewportBuilder,
+    ViewportId,
+};
+use std::collections::HashSet;
+use std::f32::consts::TAU;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Everything a cat is drawn from, besides its own animations
+pub struct Scene<'a> {
+    pub config: &'a Config,
+    /// The cat's own window options
+    pub window: &'a WindowConfig,
+    pub keystroke_state: &'a KeystrokeState,
+    pub themes: &'a ThemeSet,
+    /// Id of the theme to draw
+    pub theme: &'a str,
+    /// Font files registered with egui
+    pub fonts: &'a HashSet<PathBuf>,
+    /// Where the focused fullscreen window is, if there is one
+    pub fullscreen: Option<Rect>,
+    pub scale: f32,
+}
+
+/// Key shapes and animations of one cat, kept between frames
+#[derive(Default)]
+pub struct CatView {
+    /// Holds shapes we don't want to keep redrawing
+    shape_holder: ShapeHolder,
+    /// Theme settings `shape_holder` was built with
+    shape_params: ShapeParams,
+    /// Scale `shape_holder` was built at
+    shape_scale: f32,
+    /// Blinking, yawning and sleeping while nobody types
+    idle: IdleAnimation,
+    /// Ripples, bounces and pops
+    effects: Effects,
+}
+
+/// Extra cats are drawn as part of the main window's frames, so it's always the one repainted
+fn repaint_after(ctx: &Context, delay: Duration) {
+    ctx.request_repaint_after_for(delay, ViewportId::ROOT);
+}
+
+/// Whether `ctx`'s window is on top of the `fullscreen` window
+pub fn covers_fullscreen(ctx: &Context, fullscreen: Option<Rect>) -> bool {
+    let Some(fullscreen) = fullscreen else {
+        return false;
+    };
+    let (outer_rect, pixels_per_point) = ctx.input(|i| {
+        (
+            i.viewport().outer_rect,
+            i.viewport().native_pixels_per_point.unwrap_or(1.0),
+        )
+    });
+    outer_rect.is_some_and(|rect| {
+        let rect = Rect::from_min_max(
+            (rect.min.to_vec2() * pixels_per_point).to_pos2(),
+            (rect.max.to_vec2() * pixels_per_point).to_pos2(),
+        );
+        rect.intersects(fullscreen)
+    })
+}
+
+impl CatView {
+    /// Draws the cat on `ctx`'s background layer
+    pub fn paint(&mut self, ctx: &Context, scene: &Scene<'_>) {
+        let Scene {
+            config,
+            window,
+            keystroke_state,
+            scale,
+            ..
+        } = *scene;
+
+        let mut painter = ctx.layer_painter(LayerId::background());
+        painter.set_opacity(window.opacity.clamp(0.0, 1.0));
+        if covers_fullscreen(ctx, scene.fullscreen) {
+            match window.over_fullscreen {
+                OverFullscreen::Show => {}
+                OverFullscreen::Hide => return,
+                OverFullscreen::Fade => painter.multiply_opacity(FULLSCREEN_OPACITY),
+            }
+        }
+        let (fade, fade_repaint_after) = idle::fade(&config.idle, keystroke_state.idle_for());
+        painter.multiply_opacity(fade);
+        if let Some(delay) = fade_repaint_after {
+            repaint_after(ctx, delay);
+        }
+
+        let paws = keystroke_state.paw_state();
+        let idle = self.idle.update(&config.idle, keystroke_state.idle_for());
+        if let Some(delay) = idle.repaint_after {
+            repaint_after(ctx, delay);
+        }
+
+        let counter = keystroke_state.counter(config.counter.show);
+        if let Some(delay) = self.effects.update(
+            &config.effects,
+            keystroke_state,
+            &self.shape_holder,
+            counter,
+        ) {
+            repaint_after(ctx, delay);
+        }
+
+        let theme = scene.themes.rendered(scene.theme, paws.o_face);
+        let default_manifest;
+        let manifest = match theme {
+            Some(theme) => &theme.manifest,
+            None => {
+                default_manifest = ThemeManifest::default();
+                &default_manifest
+            }
+        };
+        let (colors, overlay) = (&manifest.colors, &manifest.overlay);
+        let shape_params = ShapeParams::of(manifest);
+        if shape_params != self.shape_params || scale != self.shape_scale {
+            self.shape_params = shape_params;
+            self.shape_scale = scale;
+            self.shape_holder = layout::shape_holder(scale, &self.shape_params);
+        }
+
+        // The window is cleared the same way for every cat, so only the main cat's background counts
+        if config.window.background == Background::Image
+            && let Some(tex) = theme.and_then(|theme| theme.background.as_ref())
+        {
+            painter.image(
+                tex.id(),
+                layout::window_rect(scale),
+                UV_RECT,
+                Color32::WHITE,
+            );
+        }
+
+        if overlay.visible {
+            for shape in self
+                .shape_holder
+                .iter()
+                .filter(|shape| !shape.key.is_spacer())
+            {
+                let (fill, outline) = overlay.colors_for(colors, shape.key, shape.row);
+                painter.add(egui::Shape::convex_polygon(
+                    shape.points.clone(),
+                    Effects::key_fill(&config.effects, keystroke_state, shape.key, fill),
+                    Stroke::new(overlay.stroke_width * scale, outline),
+                ));
+            }
+        }
+
+        let text = format!("{counter}");
+        let style = &manifest.counter;
+        let family = [&config.counter.font, &style.font]
+            .into_iter()
+            .flatten()
+            .find(|font| scene.fonts.contains(*font))
+            .map_or(FontFamily::Proportional, |font| fonts::family(font));
+        let galley = painter.layout_job(LayoutJob {
+            sections: vec![LayoutSection {
+                leading_space: 0.0,
+                byte_range: 0usize..text.len(),
+                format: TextFormat::simple(
+                    FontId::new(
+                        style.size * scale * self.effects.counter_scale(&config.effects),
+                        family,
+                    ),
+                    colors.counter.0,
+                ),
+            }],
+            text,
+            halign: style.align.into(),
+            ..Default::default()
+        });
+        let position = layout::counter_position(style, scale);
+        let angle = style.angle.to_radians();
+        let text_at = |offset: Vec2, color: Option<Color32>| {
+            let shape = TextShape::new(position + offset, galley.clone(), Color32::PLACEHOLDER)
+                .with_angle(angle);
+            match color {
+                Some(color) => shape.with_override_text_color(color),
+                None => shape,
+            }
+        };
+
+        if let Some(shadow) = style.shadow {
+            painter.add(text_at(
+                Vec2::from(style.shadow_offset) * scale,
+                Some(shadow.0),
+            ));
+        }
+        if let Some(outline) = style.outline {
+            // Stamp the text around itself to fake an outline
+            for i in 0..8 {
+                let offset = Vec2::angled(TAU * i as f32 / 8.0) * style.outline_width * scale;
+                painter.add(text_at(offset, Some(outline.0)));
+            }
+        }
+        painter.add(text_at(Vec2::ZERO, None));
+
+        if let Some(theme) = theme {
+            let id = if let Some(tex) = idle.frame.and_then(|frame| theme.idle.get(frame)) {
+                tex.id()
+            } else if paws.left
+                && paws.right
+                && let Some(tex) = &theme.paws_both
+            {
+                tex.id()
+            } else if paws.left
+                && let Some(tex) = &theme.paws_left
+            {
+                tex.id()
+            } else if paws.right
+                && let Some(tex) = &theme.paws_right
+            {
+                tex.id()
+            } else if let Some(tex) = &theme.paws_up {
+                tex.id()
+            } else {
+                TextureId::default()
+            };
+
+            painter.image(
+                id,
+                layout::window_rect(scale)
+                    .translate(self.effects.cat_offset(&config.effects, scale)),
+                UV_RECT,
+                Color32::WHITE,
+            );
+        }
+
+        self.effects
+            .paint_ripples(&painter, &self.shape_holder, colors.ripple.0, scale);
+
+        if idle.asleep {
+            // One more z every step, then start over
+            let step = keystroke_state.idle_for().as_millis() / ZZZ_STEP.as_millis();
+            let text = &"Zzz"[..(step % 3) as usize + 1];
+            painter.text(
+                layout::zzz_position(scale),
+                Align2::LEFT_BOTTOM,
+                text,
+                FontId::new(layout::zzz_font_size(scale), FontFamily::Proportional),
+                Color32::WHITE,
+            );
+        }
+    }
+}
+
+/// A cat in a window of its own, next to the main one
+pub struct ExtraCat {
+    pub config: CatConfig,
+    /// The main cat's, or the cat's own when it only reacts to one keyboard
+    pub keystroke_state: Arc<KeystrokeState>,
+    view: CatView,
+    viewport_id: ViewportId,
+    /// Where the window opens, if its display was found
+    position: Option<Pos2>,
+}
+
+impl ExtraCat {
+    #[must_use]
+    pub fn new(
+        index: usize,
+        config: CatConfig,
+        keystroke_state: Arc<KeystrokeState>,
+        displays: &[DisplayInfo],
+    ) -> Self {
+        let size = layout::window_size(config.window.scale.clamp(MIN_SCALE, MAX_SCALE));
+        Self {
+            position: placement::window_position(&config.window, displays, size, None),
+            config,
+            keystroke_state,
+            view: CatView::default(),
+            viewport_id: ViewportId::from_hash_of(("cat", index)),
+        }
+    }
+
+    /// Draws the cat in its window, opening the window the first time. `main_theme` is used unless the
+    /// cat has a theme of its own.
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        config: &Config,
+        themes: &ThemeSet,
+        main_theme: &str,
+        fonts: &HashSet<PathBuf>,
+        fullscreen: Option<Rect>,
+    ) {
+        let scale = self.config.window.scale.clamp(MIN_SCALE, MAX_SCALE);
+        let size = layout::window_size(scale);
+        let mut builder = ViewportBuilder::default()
+            .with_title(APP_ID)
+            .with_transparent(true)
+            .with_inner_size(size)
+            .with_min_inner_size(size)
+            .with_max_inner_size(size)
+            .with_always_on_top()
+            .with_decorations(false)
+            .with_resizable(false)
+            .with_mouse_passthrough(true)
+            .with_drag_and_drop(false);
+        if let Some(position) = self.position {
+            builder = builder.with_position(position);
+        }
+
+        let scene = Scene {
+            config,
+            window: &self.config.window,
+            keystroke_state: &self.keystroke_state,
+            themes,
+            theme: self.config.theme.as_deref().unwrap_or(main_theme),
+            fonts,
+            fullscreen,
+            scale,
+        };
+        let view = &mut self.view;
+        ctx.show_viewport_immediate(self.viewport_id, builder, |ctx, _class| {
+            view.paint(ctx, &scene);
+        });
+    }
+}
@@ -0,0 +1,77 @@
+use crate::config::Background;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, Sender, channel};
+
+/// Something the UI thread should do, requested from anywhere else
+#[derive(Clone, Debug, PartialEq)]
+pub enum AppCommand {
+    /// Set the window scale factor
+    SetScale(f32),
+    /// Step the window scale factor up
+    ScaleUp,
+    /// Step the window scale factor down
+    ScaleDown,
+    /// Toggle dragging the window around
+    ToggleEditMode,
+    /// Show or hide the cat
+    ToggleVisibility,
+    /// Switch to the next theme
+    CycleTheme,
+    /// Set the session keystroke count back to zero
+    ResetSession,
+    /// Stop or resume counting keystrokes
+    TogglePause,
+    /// Mute or unmute sound effects
+    ToggleMute,
+    /// Switch to the next background
+    CycleBackground,
+    /// Switch to this background
+    SetBackground(Background),
+    /// Switch to the theme with this id
+    SetTheme(String),
+    /// Stop offering the theme with this id, which could not be decoded
+    ForgetTheme(String),
+    /// Open the stats window
+    OpenStats,
+    /// Open the settings window
+    OpenSettings,
+    /// Close the app
+    Quit,
+}
+
+/// Queue of `AppCommand`s, drained at the start of each frame
+#[derive(Clone)]
+pub struct CommandQueue {
+    sender: Sender<AppCommand>,
+    receiver: Arc<Mutex<Receiver<AppCommand>>>,
+}
+
+impl Default for CommandQueue {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+        }
+    }
+}
+
+impl CommandQueue {
+    /// A handle for sending commands from other threads
+    #[must_use]
+    pub fn sender(&self) -> Sender<AppCommand> {
+        self.sender.clone()
+    }
+
+    pub fn send(&self, command: AppCommand) {
+        // The receiver lives as long as `self`, so this can't fail
+        let _ = self.sender.send(command);
+    }
+
+    /// Takes every command queued so far
+    #[must_use]
+    pub fn drain(&self) -> Vec<AppCommand> {
+        self.receiver.lock().try_iter().collect()
+    }
+}
@@ -0,0 +1,230 @@
+//! Eye candy on top of the key highlights: fading keys, ripples, paw bounces and counter pops
+use crate::app::keystroke::{KeystrokeState, paw_for};
+use crate::app::layout::ShapeHolder;
+use crate::config::EffectsConfig;
+use crate::consts::keyboard::{KEY_PRESSED_LIT_DELAY, VisualKeybdKeyHolder};
+use egui::{Color32, Painter, Pos2, Stroke, Vec2};
+use inputbot::KeybdKey;
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+const RIPPLE_DURATION: Duration = Duration::from_millis(400);
+/// How far a ripple grows, before scaling
+const RIPPLE_RADIUS: f32 = 14.0;
+const PARTICLES: usize = 6;
+const BOUNCE_DURATION: Duration = Duration::from_millis(120);
+/// How far the cat dips on a slap, before scaling
+const BOUNCE_DEPTH: f32 = 2.0;
+const MILESTONE_DURATION: Duration = Duration::from_millis(600);
+/// How far the cat shakes on a milestone, before scaling
+const SHAKE_AMPLITUDE: f32 = 3.0;
+/// How much bigger the counter gets on a milestone
+const POP_SCALE: f32 = 0.5;
+/// Redraw rate while something is animating
+const FRAME: Duration = Duration::from_millis(16);
+
+/// How far along an effect started at `started` and lasting `duration` is, from 0 to 1
+fn progress(started: Instant, duration: Duration) -> Option<f32> {
+    let t = started.elapsed().as_secs_f32() / duration.as_secs_f32();
+    (t < 1.0).then_some(t)
+}
+
+/// Timing of every running effect
+#[derive(Default)]
+pub struct Effects {
+    /// Press times already turned into effects
+    seen: HashMap<KeybdKey, Instant>,
+    ripples: Vec<(&'static VisualKeybdKeyHolder, Instant)>,
+    bounce: Option<Instant>,
+    milestone: Option<Instant>,
+    /// Milestones reached by the counter, once it has been seen
+    milestones_reached: Option<usize>,
+}
+
+impl Effects {
+    /// Starts effects for new key presses and milestones, returning when to redraw if anything is animating
+    pub fn update(
+        &mut self,
+        config: &EffectsConfig,
+        state: &KeystrokeState,
+        shape_holder: &ShapeHolder,
+        counter: usize,
+    ) -> Option<Duration> {
+        for shape in shape_holder {
+            let key = shape.key;
+            if paw_for(key).is_none() {
+                continue;
+            }
+            let Some(pressed) = state.last_pressed_map.get(&key.key).map(|p| *p) else {
+                continue;
+            };
+            if self.seen.insert(key.key, pressed) != Some(pressed) {
+                if config.ripples {
+                    self.ripples.push((key, pressed));
+                }
+                if config.bounce {
+                    self.bounce = Some(pressed);
+                }
+            }
+        }
+        self.ripples
+            .retain(|(_, started)| progress(*started, RIPPLE_DURATION).is_some());
+
+        if config.milestone > 0 {
+            let reached = counter / config.milestone;
+            if self
+                .milestones_reached
+                .is_some_and(|before| reached > before)
+                && (config.bounce || config.counter_pop)
+            {
+                self.milestone = Some(Instant::now());
+            }
+            self.milestones_reached = Some(reached);
+        }
+
+        let animating = !self.ripples.is_empty()
+            || self
+                .bounce
+                .is_some_and(|started| progress(started, BOUNCE_DURATION).is_some())
+            || self
+                .milestone
+                .is_some_and(|started| progress(started, MILESTONE_DURATION).is_some())
+            || (config.fade && !state.last_pressed_map.is_empty());
+        animating.then_some(FRAME)
+    }
+
+    /// Fill of a key, fading out after it was pressed if `fade` is set
+    #[must_use]
+    pub fn key_fill(
+        config: &EffectsConfig,
+        state: &KeystrokeState,
+        key: &VisualKeybdKeyHolder,
+        color: Color32,
+    ) -> Color32 {
+        if paw_for(key).is_none() || !state.lit_keys_map[key.key].load(Ordering::Relaxed) {
+            return Color32::TRANSPARENT;
+        }
+        if !config.fade {
+            return color;
+        }
+
+        let t = state
+            .last_pressed_map
+            .get(&key.key)
+            .and_then(|pressed| progress(*pressed, KEY_PRESSED_LIT_DELAY))
+            .unwrap_or(1.0);
+        color.gamma_multiply(1.0 - t)
+    }
+
+    /// Draws a ring and a burst of particles around each recently pressed key
+    pub fn paint_ripples(
+        &self,
+        painter: &Painter,
+        shape_holder: &ShapeHolder,
+        color: Color32,
+        scale: f32,
+    ) {
+        for (key, started) in &self.ripples {
+            let Some(t) = progress(*started, RIPPLE_DURATION) else {
+                continue;
+            };
+            let Some(points) = shape_holder
+                .iter()
+                .find(|shape| std::ptr::eq(shape.key, *key))
+                .map(|shape| &shape.points)
+            else {
+                continue;
+            };
+
+            let center = Pos2::new(
+                points.iter().map(|p| p.x).sum::<f32>() / points.len() as f32,
+                points.iter().map(|p| p.y).sum::<f32>() / points.len() as f32,
+            );
+            let radius = RIPPLE_RADIUS * scale * t;
+            let color = color.gamma_multiply(1.0 - t);
+
+            painter.circle_stroke(center, radius, Stroke::new(scale, color));
+            for i in 0..PARTICLES {
+                let angle = TAU * i as f32 / PARTICLES as f32;
+                let offset = Vec2::angled(angle) * radius * 1.4;
+                painter.circle_filled(center + offset, scale * (1.0 - t), color);
+            }
+        }
+    }
+
+    /// Where to draw the cat relative to its usual spot: a dip on slaps, a shake on milestones
+    #[must_use]
+    pub fn cat_offset(&self, config: &EffectsConfig, scale: f32) -> Vec2 {
+        let mut offset = Vec2::ZERO;
+        if config.bounce
+            && let Some(t) = self
+                .bounce
+                .and_then(|started| progress(started, BOUNCE_DURATION))
+        {
+            offset.y += BOUNCE_DEPTH * scale * (1.0 - t);
+        }
+        if config.bounce
+            && let Some(t) = self
+                .milestone
+                .and_then(|started| progress(started, MILESTONE_DURATION))
+        {
+            offset.x += SHAKE_AMPLITUDE * scale * (1.0 - t) * (t * TAU * 6.0).sin();
+        }
+        offset
+    }
+
+    /// How much bigger than usual to draw the counter
+    #[must_use]
+    pub fn counter_scale(&self, config: &EffectsConfig) -> f32 {
+        match self
+            .milestone
+            .and_then(|started| progress(started, MILESTONE_DURATION))
+        {
+            Some(t) if config.counter_pop => 1.0 + POP_SCALE * (1.0 - t),
+            _ => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::layout::{self, ShapeParams};
+
+    fn after_a_keystroke(config: &EffectsConfig) -> Option<Duration> {
+        let state = KeystrokeState::default();
+        let shape_holder = layout::shape_holder(1.0, &ShapeParams::default());
+        let mut effects = Effects::default();
+        effects.update(config, &state, &shape_holder, 0);
+        state.log_keystroke(&KeybdKey::AKey);
+        effects.update(config, &state, &shape_holder, 1)
+    }
+
+    #[test]
+    fn animates_only_the_effects_that_are_on() {
+        let off = EffectsConfig {
+            fade: false,
+            ripples: false,
+            bounce: false,
+            counter_pop: false,
+            milestone: 1,
+        };
+        assert_eq!(after_a_keystroke(&off), None);
+        assert_eq!(
+            after_a_keystroke(&EffectsConfig {
+                bounce: true,
+                ..off.clone()
+            }),
+            Some(FRAME)
+        );
+        assert_eq!(
+            after_a_keystroke(&EffectsConfig {
+                ripples: true,
+                ..off
+            }),
+            Some(FRAME)
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Font files from the config and themes, registered with egui as their own families
+use ab_glyph::FontRef;
+use egui::{Context, FontData, FontDefinitions, FontFamily};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The family `path` is registered as
+#[must_use]
+pub fn family(path: &Path) -> FontFamily {
+    FontFamily::Name(path.to_string_lossy().into())
+}
+
+/// Registers every font in `paths` with `ctx`, falling back to the default fonts for missing glyphs.
+/// Returns the paths that loaded, since drawing with an unregistered family panics.
+pub fn register<'a>(ctx: &Context, paths: impl IntoIterator<Item = &'a Path>) -> HashSet<PathBuf> {
+    let mut definitions = FontDefinitions::default();
+    let fallback = definitions
+        .families
+        .get(&FontFamily::Proportional)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut loaded = HashSet::new();
+    for path in paths {
+        if loaded.contains(path) {
+            continue;
+        }
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                log::warn!("Could not read font {}: {e}", path.display());
+                continue;
+            }
+        };
+        // egui panics on fonts it can't parse, so parse them the same way first
+        if let Err(e) = FontRef::try_from_slice(&bytes) {
+            log::warn!("{} is not a usable TTF or OTF font: {e}", path.display());
+            continue;
+        }
+
+        let name = path.to_string_lossy().into_owned();
+        definitions
+            .font_data
+            .insert(name.clone(), Arc::new(FontData::from_owned(bytes)));
+        let mut fonts = vec![name];
+        fonts.extend(fallback.iter().cloned());
+        definitions.families.insert(family(path), fonts);
+        loaded.insert(path.to_path_buf());
+    }
+
+    if !loaded.is_empty() {
+        ctx.set_fonts(definitions);
+    }
+    loaded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_files_that_only_look_like_fonts() {
+        let path = std::env::temp_dir().join(format!("bongocat-broken-{}.ttf", std::process::id()));
+        // The TrueType magic, followed by a table directory that doesn't add up
+        std::fs::write(&path, b"\x00\x01\x00\x00\x00\x09garbage").unwrap();
+        let loaded = register(&Context::default(), [path.as_path()]);
+        std::fs::remove_file(&path).unwrap();
+        assert!(loaded.is_empty());
+    }
+}
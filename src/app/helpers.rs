@@ -1,5 +1,5 @@
 use egui::{ColorImage, Context, TextureHandle, TextureOptions};
-use image::DynamicImage;
+use image::{DynamicImage, ImageResult};
 use std::path::Path;
 
 pub fn load_texture_from_color_image(
@@ -14,9 +14,9 @@ pub fn load_texture_from_color_image(
     )
 }
 
-pub fn load_color_image_from_path(image: &impl AsRef<Path>) -> ColorImage {
-    let image = image::ImageReader::open(image).unwrap().decode().unwrap();
-    color_image_from_dynamic(image)
+pub fn load_color_image_from_path(image: &impl AsRef<Path>) -> ImageResult<ColorImage> {
+    let image = image::ImageReader::open(image)?.decode()?;
+    Ok(color_image_from_dynamic(image))
 }
 
 pub fn color_image_from_dynamic(image: DynamicImage) -> ColorImage {
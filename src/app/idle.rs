@@ -0,0 +1,234 @@
+//! What the cat does while nobody is typing: blinks, yawns, then falls asleep until the next keystroke
+use crate::config::IdleConfig;
+use crate::theme::IdleFrame;
+use std::time::{Duration, Instant};
+
+/// Time without keystrokes before the cat starts blinking
+const BLINK_AFTER: Duration = Duration::from_secs(3);
+/// Time between the starts of two blinks
+const BLINK_INTERVAL: Duration = Duration::from_secs(4);
+const BLINK_DURATION: Duration = Duration::from_millis(150);
+const YAWN_DURATION: Duration = Duration::from_millis(1500);
+const WAKE_DURATION: Duration = Duration::from_millis(400);
+/// Time between two steps of the "Zzz" animation
+pub const ZZZ_STEP: Duration = Duration::from_millis(500);
+const FADE_DURATION: Duration = Duration::from_secs(1);
+/// Redraw rate while fading out
+const FADE_FRAME: Duration = Duration::from_millis(33);
+
+/// What to draw for the current idle state
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IdleView {
+    /// Frame to show instead of the paws, if the theme has it
+    pub frame: Option<IdleFrame>,
+    /// Whether to draw "Zzz"
+    pub asleep: bool,
+    /// When the view changes next, if it will without a keystroke
+    pub repaint_after: Option<Duration>,
+}
+
+/// Remembers whether the cat was asleep, to play the wake-up frame once it isn't
+#[derive(Debug, Default)]
+pub struct IdleAnimation {
+    asleep: bool,
+    woke_at: Option<Instant>,
+}
+
+impl IdleAnimation {
+    /// The view after `idle_for` without keystrokes
+    pub fn update(&mut self, config: &IdleConfig, idle_for: Duration) -> IdleView {
+        if !config.enabled {
+            return IdleView::default();
+        }
+
+        let sleep_after = Duration::from_secs(config.sleep_after_secs);
+        if idle_for >= sleep_after {
+            self.asleep = true;
+            return IdleView {
+                frame: Some(IdleFrame::Sleep),
+                asleep: true,
+                repaint_after: Some(ZZZ_STEP),
+            };
+        }
+
+        if self.asleep {
+            self.asleep = false;
+            self.woke_at = Some(Instant::now());
+        }
+        if let Some(woke_at) = self.woke_at {
+            let awake_for = woke_at.elapsed();
+            if awake_for < WAKE_DURATION {
+                return IdleView {
+                    frame: Some(IdleFrame::Wake),
+                    asleep: false,
+                    repaint_after: Some(WAKE_DURATION - awake_for),
+                };
+            }
+            self.woke_at = None;
+        }
+
+        // Whatever happens, check back when the cat should fall asleep
+        let mut next = sleep_after - idle_for;
+
+        let yawn_after = Duration::from_secs(config.yawn_after_secs);
+        if idle_for < yawn_after {
+            next = next.min(yawn_after - idle_for);
+        } else if idle_for < yawn_after + YAWN_DURATION {
+            return IdleView {
+                frame: Some(IdleFrame::Yawn),
+                asleep: false,
+                repaint_after: Some(next.min(yawn_after + YAWN_DURATION - idle_for)),
+            };
+        }
+
+        if idle_for < BLINK_AFTER {
+            return IdleView {
+                frame: None,
+                asleep: false,
+                repaint_after: Some(next.min(BLINK_AFTER - idle_for)),
+            };
+        }
+
+        let since_blink = Duration::from_millis(
+            ((idle_for - BLINK_AFTER).as_millis() % BLINK_INTERVAL.as_millis()) as u64,
+        );
+        if since_blink < BLINK_DURATION {
+            IdleView {
+                frame: Some(IdleFrame::Blink),
+                asleep: false,
+                repaint_after: Some(next.min(BLINK_DURATION - since_blink)),
+            }
+        } else {
+            IdleView {
+                frame: None,
+                asleep: false,
+                repaint_after: Some(next.min(BLINK_INTERVAL - since_blink)),
+            }
+        }
+    }
+}
+
+/// How opaque the cat is after `idle_for` without keystrokes, relative to its usual opacity, and when that
+/// changes next
+#[must_use]
+pub fn fade(config: &IdleConfig, idle_for: Duration) -> (f32, Option<Duration>) {
+    if config.fade_after_secs == 0 {
+        return (1.0, None);
+    }
+
+    let fade_after = Duration::from_secs(config.fade_after_secs);
+    let Some(faded_for) = idle_for.checked_sub(fade_after) else {
+        return (1.0, Some(fade_after - idle_for));
+    };
+    let t = (faded_for.as_secs_f32() / FADE_DURATION.as_secs_f32()).min(1.0);
+    let faded_opacity = config.faded_opacity.clamp(0.0, 1.0);
+    (
+        1.0 + (faded_opacity - 1.0) * t,
+        (t < 1.0).then_some(FADE_FRAME),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn does_nothing_when_disabled() {
+        let config = IdleConfig {
+            enabled: false,
+            ..IdleConfig::default()
+        };
+        let mut idle = IdleAnimation::default();
+        assert_eq!(idle.update(&config, secs(1000)), IdleView::default());
+    }
+
+    #[test]
+    fn blinks_yawns_then_sleeps() {
+        let config = IdleConfig::default();
+        let mut idle = IdleAnimation::default();
+
+        let typing = idle.update(&config, Duration::ZERO);
+        assert_eq!(typing.frame, None);
+        assert_eq!(typing.repaint_after, Some(BLINK_AFTER));
+
+        let blink = idle.update(&config, BLINK_AFTER + BLINK_INTERVAL);
+        assert_eq!(blink.frame, Some(IdleFrame::Blink));
+        assert_eq!(blink.repaint_after, Some(BLINK_DURATION));
+
+        let between = idle.update(&config, BLINK_AFTER + BLINK_DURATION);
+        assert_eq!(between.frame, None);
+        assert_eq!(between.repaint_after, Some(BLINK_INTERVAL - BLINK_DURATION));
+
+        let yawn = idle.update(&config, secs(config.yawn_after_secs));
+        assert_eq!(yawn.frame, Some(IdleFrame::Yawn));
+        assert_eq!(yawn.repaint_after, Some(YAWN_DURATION));
+
+        let asleep = idle.update(&config, secs(config.sleep_after_secs));
+        assert_eq!(asleep.frame, Some(IdleFrame::Sleep));
+        assert!(asleep.asleep);
+        assert_eq!(asleep.repaint_after, Some(ZZZ_STEP));
+    }
+
+    #[test]
+    fn always_checks_back_before_falling_asleep() {
+        let config = IdleConfig::default();
+        let mut idle = IdleAnimation::default();
+        let almost = secs(config.sleep_after_secs) - Duration::from_millis(10);
+        let view = idle.update(&config, almost);
+        assert_eq!(view.repaint_after, Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn wakes_up_after_sleeping() {
+        let config = IdleConfig::default();
+        let mut idle = IdleAnimation::default();
+        idle.update(&config, secs(config.sleep_after_secs));
+
+        let waking = idle.update(&config, Duration::ZERO);
+        assert_eq!(waking.frame, Some(IdleFrame::Wake));
+        assert!(!waking.asleep);
+        assert!(
+            waking
+                .repaint_after
+                .is_some_and(|after| after <= WAKE_DURATION)
+        );
+    }
+
+    #[test]
+    fn never_fades_by_default() {
+        assert_eq!(fade(&IdleConfig::default(), secs(10_000)), (1.0, None));
+    }
+
+    #[test]
+    fn fades_out_over_a_second() {
+        let config = IdleConfig {
+            fade_after_secs: 10,
+            faded_opacity: 0.2,
+            ..IdleConfig::default()
+        };
+        assert_eq!(fade(&config, secs(4)), (1.0, Some(secs(6))));
+        assert_eq!(fade(&config, secs(10)), (1.0, Some(FADE_FRAME)));
+
+        let (halfway, repaint_after) = fade(&config, secs(10) + FADE_DURATION / 2);
+        assert!((halfway - 0.6).abs() < 1e-6);
+        assert_eq!(repaint_after, Some(FADE_FRAME));
+
+        let (faded, repaint_after) = fade(&config, secs(12));
+        assert!((faded - 0.2).abs() < 1e-6);
+        assert_eq!(repaint_after, None);
+    }
+
+    #[test]
+    fn clamps_the_faded_opacity() {
+        let config = IdleConfig {
+            fade_after_secs: 1,
+            faded_opacity: -1.0,
+            ..IdleConfig::default()
+        };
+        assert_eq!(fade(&config, secs(5)), (0.0, None));
+    }
+}
@@ -1,27 +1,200 @@
+use crate::config::{CounterMode, PrivacyMode};
+use crate::consts::keyboard::{KEYS, VisualKeybdKeyHolder};
 use dashmap::DashMap;
-use enum_map::EnumMap;
+use enum_map::{Enum, EnumMap};
 use inputbot::KeybdKey;
 use parking_lot::Mutex;
+use std::collections::VecDeque;
 use std::ops::Deref;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
 use std::thread::Thread;
 use std::time::{Duration, Instant};
 
+/// One of the cat's paws, or both
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Paw {
+    Left,
+    Right,
+    #[default]
+    Both,
+}
+
+/// Which paw slaps `key`, or `None` for spacers
+#[must_use]
+pub fn paw_for(key: &VisualKeybdKeyHolder) -> Option<Paw> {
+    match key.key {
+        _ if !key.is_mapped() => None,
+        KeybdKey::OtherKey(_) if key.column < 10 => Some(Paw::Left),
+        KeybdKey::OtherKey(_) => Some(Paw::Right),
+        _ if key.column < 8 => Some(Paw::Left),
+        _ => Some(Paw::Right),
+    }
+}
+
+/// Which paw slaps `key`, or `None` if it isn't drawn on the keyboard
+#[must_use]
+pub fn paw_for_key(key: KeybdKey) -> Option<Paw> {
+    KEYS.iter()
+        .flat_map(|row| row.iter())
+        .find(|holder| holder.key == key)
+        .and_then(paw_for)
+}
+
+/// Whether holding `key` makes the cat's "o" face
+#[must_use]
+pub fn is_o_face_key(key: KeybdKey) -> bool {
+    matches!(
+        key,
+        KeybdKey::OKey | KeybdKey::Numrow0Key | KeybdKey::Numpad0Key
+    )
+}
+
+/// Coarse group of keys, counted instead of single keys in `PrivacyMode::Classes`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Enum, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyClass {
+    Letter,
+    Digit,
+    Symbol,
+    /// Space, enter, tab and backspace
+    Whitespace,
+    Modifier,
+    /// Arrows, home, end, page up/down, insert and delete
+    Navigation,
+    /// Function keys and escape
+    Function,
+    Other,
+}
+
+impl KeyClass {
+    #[must_use]
+    pub fn of(key: KeybdKey) -> Self {
+        use KeybdKey::*;
+        match key {
+            AKey | BKey | CKey | DKey | EKey | FKey | GKey | HKey | IKey | JKey | KKey | LKey
+            | MKey | NKey | OKey | PKey | QKey | RKey | SKey | TKey | UKey | VKey | WKey | XKey
+            | YKey | ZKey => Self::Letter,
+            Numrow0Key | Numrow1Key | Numrow2Key | Numrow3Key | Numrow4Key | Numrow5Key
+            | Numrow6Key | Numrow7Key | Numrow8Key | Numrow9Key | Numpad0Key | Numpad1Key
+            | Numpad2Key | Numpad3Key | Numpad4Key | Numpad5Key | Numpad6Key | Numpad7Key
+            | Numpad8Key | Numpad9Key => Self::Digit,
+            BackquoteKey | MinusKey | EqualKey | LBracketKey | RBracketKey | BackslashKey
+            | SemicolonKey | QuoteKey | CommaKey | PeriodKey | SlashKey => Self::Symbol,
+            SpaceKey | EnterKey | TabKey | BackspaceKey => Self::Whitespace,
+            LShiftKey | RShiftKey | LControlKey | RControlKey | LAltKey | RAltKey | LSuper
+            | RSuper | CapsLockKey | NumLockKey | ScrollLockKey => Self::Modifier,
+            UpKey | DownKey | LeftKey | RightKey | HomeKey | EndKey | PageUpKey | PageDownKey
+            | InsertKey | DeleteKey => Self::Navigation,
+            F1Key | F2Key | F3Key | F4Key | F5Key | F6Key | F7Key | F8Key | F9Key | F10Key
+            | F11Key | F12Key | EscapeKey => Self::Function,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Why counting is paused; several can apply at once
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseReason {
+    /// Paused from a hotkey, the tray, the settings window or the control socket
+    Manual,
+    /// A window matching `privacy.pause_in` is focused
+    FocusedApp,
+    /// The session is locked or the screensaver is on
+    Locked,
+}
+
+impl PauseReason {
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Which paws are down, and whether the cat is making an "o" face
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct PawState {
+    pub left: bool,
+    pub right: bool,
+    pub o_face: bool,
+}
+
+/// Something that happened to the keystroke state, sent to subscribers
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum KeystrokeEvent {
+    /// A key was pressed and counted. `key` is only sent with `PrivacyMode::Off`.
+    KeyPressed {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        key: Option<KeybdKey>,
+        keystrokes: usize,
+        session_keystrokes: usize,
+    },
+    /// Paws went up or down. `o_face` is only sent with `PrivacyMode::Off`; with `PrivacyMode::Aggregate`
+    /// both paws go up and down together.
+    Paws(PawState),
+    /// A paw came down on a key, counted or not. `reaction` is set for keys that make the "o" face, and only
+    /// sent with `PrivacyMode::Off`; with `PrivacyMode::Aggregate` the paw is always `Both`.
+    Slap { paw: Paw, reaction: bool },
+    /// Counting stopped or started again
+    Paused { paused: bool },
+    /// The session count went back to zero
+    SessionReset,
+    /// The app switched themes
+    Theme { theme: String },
+}
+
 /// The keystroke-related application state
 #[derive(serde::Deserialize, serde::Serialize, Debug)]
 #[serde(default)]
 pub struct KeystrokeState {
     /// Number of total keystrokes ever
     pub(crate) keystrokes: AtomicUsize,
-    /// Number of keystrokes per key
+    /// Number of keystrokes since launch or the last reset
+    #[serde(skip)]
+    pub(crate) session_keystrokes: AtomicUsize,
+    /// Bits of each `PauseReason` currently keeping keystrokes from being counted
+    #[serde(skip)]
+    pub(crate) pause_reasons: AtomicU8,
+    /// Number of keystrokes per key, empty unless privacy mode is off
     pub(crate) keystroke_map: EnumMap<KeybdKey, AtomicUsize>,
+    /// Number of keystrokes per key class, empty in aggregate privacy mode
+    pub(crate) class_map: EnumMap<KeyClass, AtomicUsize>,
+    /// How much detail about keys is kept
+    #[serde(skip)]
+    pub(crate) privacy: PrivacyMode,
     #[serde(skip)]
     /// If the keys are lit or not
     pub(crate) lit_keys_map: EnumMap<KeybdKey, AtomicBool>,
     /// Last time keys were pressed
     #[serde(skip)]
     pub(crate) last_pressed_map: DashMap<KeybdKey, Instant>,
-    /// Thread for ui input updates
+    /// When the most recent keystrokes happened, for keystrokes per minute
+    #[serde(skip)]
+    pub(crate) recent_keystrokes: Mutex<VecDeque<Instant>>,
+    /// When the app started
+    #[serde(skip)]
+    pub(crate) started: Instant,
+    /// When a key was last lit up
+    #[serde(skip)]
+    pub(crate) last_activity: Mutex<Instant>,
+    /// Paws as of the last `KeystrokeEvent::Paws`
+    #[serde(skip)]
+    pub(crate) paws: Mutex<PawState>,
+    /// Where `KeystrokeEvent`s are sent
+    #[serde(skip)]
+    pub(crate) subscribers: Mutex<Vec<Sender<KeystrokeEvent>>>,
+    /// Thread that turns lit keys off again, woken whenever a key lights up
     #[serde(skip)]
     pub(crate) input_update_thread: Mutex<Option<Thread>>,
 }
@@ -30,40 +203,468 @@ impl Default for KeystrokeState {
     fn default() -> Self {
         Self {
             keystrokes: AtomicUsize::new(0),
+            session_keystrokes: AtomicUsize::new(0),
+            pause_reasons: AtomicU8::new(0),
             keystroke_map: EnumMap::default(),
+            class_map: EnumMap::default(),
+            privacy: PrivacyMode::default(),
             lit_keys_map: Default::default(),
             last_pressed_map: DashMap::new(),
+            recent_keystrokes: Mutex::default(),
+            started: Instant::now(),
+            last_activity: Mutex::new(Instant::now()),
+            paws: Mutex::default(),
+            subscribers: Mutex::default(),
             input_update_thread: Mutex::default(),
         }
     }
 }
 
 impl KeystrokeState {
+    /// Switches privacy mode, forgetting the counts it no longer allows so they aren't saved again
+    pub fn set_privacy(&mut self, privacy: PrivacyMode) {
+        self.privacy = privacy;
+        if privacy != PrivacyMode::Off {
+            self.keystroke_map
+                .values()
+                .for_each(|count| count.store(0, Ordering::Relaxed));
+        }
+        if privacy == PrivacyMode::Aggregate {
+            self.class_map
+                .values()
+                .for_each(|count| count.store(0, Ordering::Relaxed));
+        }
+    }
+
+    /// Counts `key` and lights it up, unless paused
     pub fn log_keystroke(&self, key: &KeybdKey) {
-        self.keystroke_map[*key].fetch_add(1, Ordering::Relaxed);
-        self.keystrokes.fetch_add(1, Ordering::Relaxed);
-        self.last_pressed_map.insert(*key, Instant::now());
+        if self.is_paused() {
+            return;
+        }
+
+        if self.privacy == PrivacyMode::Off {
+            self.keystroke_map[*key].fetch_add(1, Ordering::Relaxed);
+        }
+        if self.privacy != PrivacyMode::Aggregate {
+            self.class_map[KeyClass::of(*key)].fetch_add(1, Ordering::Relaxed);
+        }
+        let keystrokes = self.keystrokes.fetch_add(1, Ordering::Relaxed) + 1;
+        let session_keystrokes = self.session_keystrokes.fetch_add(1, Ordering::Relaxed) + 1;
+
+        let mut recent = self.recent_keystrokes.lock();
+        recent.push_back(Instant::now());
+        prune_recent(&mut recent);
+        drop(recent);
+
+        self.emit(KeystrokeEvent::KeyPressed {
+            key: (self.privacy == PrivacyMode::Off).then_some(*key),
+            keystrokes,
+            session_keystrokes,
+        });
+
+        self.light(*key);
+    }
+
+    /// Starts receiving every `KeystrokeEvent` from now on
+    pub fn subscribe(&self) -> Receiver<KeystrokeEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.lock().push(sender);
+        receiver
+    }
+
+    fn emit(&self, event: KeystrokeEvent) {
+        self.subscribers
+            .lock()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
 
-        self.lit_keys_map[*key].store(true, Ordering::Relaxed);
+    /// Which paws are down right now
+    pub fn paw_state(&self) -> PawState {
+        let mut paws = PawState::default();
+        for key in KEYS.iter().flat_map(|row| row.iter()) {
+            let Some(paw) = paw_for(key) else {
+                continue;
+            };
+            if !self.lit_keys_map[key.key].load(Ordering::Relaxed) {
+                continue;
+            }
+
+            match paw {
+                Paw::Left => paws.left = true,
+                _ => paws.right = true,
+            }
+            paws.o_face |= is_o_face_key(key.key);
+        }
+        paws
+    }
+
+    /// Which paws are down right now, with no more detail than the privacy mode allows
+    pub fn published_paws(&self) -> PawState {
+        let paws = self.paw_state();
+        match self.privacy {
+            PrivacyMode::Off => paws,
+            PrivacyMode::Classes => PawState {
+                o_face: false,
+                ..paws
+            },
+            PrivacyMode::Aggregate => PawState {
+                left: paws.left || paws.right,
+                right: paws.left || paws.right,
+                o_face: false,
+            },
+        }
+    }
+
+    /// Tells subscribers if the paws changed since last time
+    fn update_paws(&self) {
+        let paws = self.published_paws();
+        let mut last = self.paws.lock();
+        if *last != paws {
+            *last = paws;
+            drop(last);
+            self.emit(KeystrokeEvent::Paws(paws));
+        }
+    }
+
+    fn light(&self, key: KeybdKey) {
+        let now = Instant::now();
+        self.last_pressed_map.insert(key, now);
+        *self.last_activity.lock() = now;
+
+        self.lit_keys_map[key].store(true, Ordering::Relaxed);
+        self.update_paws();
+        if let Some(paw) = paw_for_key(key) {
+            self.emit(self.slap_event(paw, key));
+        }
 
         if let Some(thread) = self.input_update_thread.lock().deref() {
             thread.unpark();
         }
     }
 
-    #[inline(always)]
-    /// `max_age`: How old from time of insertion the values can be
-    pub fn cleanup_outdated(&self, max_age: Duration) {
+    /// The `Slap` for `key`, with no more detail than the privacy mode allows
+    fn slap_event(&self, paw: Paw, key: KeybdKey) -> KeystrokeEvent {
+        match self.privacy {
+            PrivacyMode::Off => KeystrokeEvent::Slap {
+                paw,
+                reaction: is_o_face_key(key),
+            },
+            PrivacyMode::Classes => KeystrokeEvent::Slap {
+                paw,
+                reaction: false,
+            },
+            PrivacyMode::Aggregate => KeystrokeEvent::Slap {
+                paw: Paw::Both,
+                reaction: false,
+            },
+        }
+    }
+
+    /// Makes the cat slap with `paw` without counting a keystroke, unless paused
+    pub fn slap(&self, paw: Paw) {
+        if self.is_paused() {
+            return;
+        }
+        if matches!(paw, Paw::Left | Paw::Both) {
+            self.light(KeybdKey::FKey);
+        }
+        if matches!(paw, Paw::Right | Paw::Both) {
+            self.light(KeybdKey::LKey);
+        }
+    }
+
+    /// Whether keystrokes are ignored for any reason
+    pub fn is_paused(&self) -> bool {
+        self.pause_reasons.load(Ordering::Relaxed) != 0
+    }
+
+    pub fn is_paused_for(&self, reason: PauseReason) -> bool {
+        self.pause_reasons.load(Ordering::Relaxed) & reason.bit() != 0
+    }
+
+    pub fn toggle_paused(&self) {
+        let before = self
+            .pause_reasons
+            .fetch_xor(PauseReason::Manual.bit(), Ordering::Relaxed);
+        self.announce_pause(before);
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.set_paused_for(PauseReason::Manual, paused);
+    }
+
+    pub fn set_paused_for(&self, reason: PauseReason, paused: bool) {
+        let before = if paused {
+            self.pause_reasons.fetch_or(reason.bit(), Ordering::Relaxed)
+        } else {
+            self.pause_reasons
+                .fetch_and(!reason.bit(), Ordering::Relaxed)
+        };
+        self.announce_pause(before);
+    }
+
+    /// Tells subscribers if counting stopped or started, given the pause reasons before the change
+    fn announce_pause(&self, before: u8) {
+        let paused = self.is_paused();
+        if paused != (before != 0) {
+            self.emit(KeystrokeEvent::Paused { paused });
+        }
+    }
+
+    pub fn reset_session(&self) {
+        self.session_keystrokes.store(0, Ordering::Relaxed);
+        self.emit(KeystrokeEvent::SessionReset);
+    }
+
+    /// Tells subscribers the app switched to theme `id`
+    pub fn announce_theme(&self, id: &str) {
+        self.emit(KeystrokeEvent::Theme {
+            theme: id.to_string(),
+        });
+    }
+
+    /// The number shown on the counter
+    pub fn counter(&self, mode: CounterMode) -> usize {
+        match mode {
+            CounterMode::Total => self.keystrokes.load(Ordering::Relaxed),
+            CounterMode::Session => self.session_keystrokes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Keystrokes over the last minute
+    pub fn keystrokes_per_minute(&self) -> usize {
+        let mut recent = self.recent_keystrokes.lock();
+        prune_recent(&mut recent);
+        recent.len()
+    }
+
+    /// Time since a key was last lit up
+    pub fn idle_for(&self) -> Duration {
+        self.last_activity.lock().elapsed()
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Keystrokes per key class, most pressed first
+    pub fn key_classes(&self) -> Vec<(KeyClass, usize)> {
+        let mut classes: Vec<(KeyClass, usize)> = self
+            .class_map
+            .iter()
+            .map(|(class, presses)| (class, presses.load(Ordering::Relaxed)))
+            .filter(|(_, presses)| *presses > 0)
+            .collect();
+        classes.sort_by(|a, b| b.1.cmp(&a.1));
+        classes
+    }
+
+    /// The `count` most pressed keys, most pressed first
+    pub fn top_keys(&self, count: usize) -> Vec<(KeybdKey, usize)> {
+        let mut keys: Vec<(KeybdKey, usize)> = self
+            .keystroke_map
+            .iter()
+            .map(|(key, presses)| (key, presses.load(Ordering::Relaxed)))
+            .filter(|(_, presses)| *presses > 0)
+            .collect();
+        keys.sort_by(|a, b| b.1.cmp(&a.1));
+        keys.truncate(count);
+        keys
+    }
+
+    /// Turns off keys lit longer than `max_age` ago. Returns when the next of the remaining lit keys
+    /// should go out, if any are left.
+    pub fn cleanup_outdated(&self, max_age: Duration) -> Option<Instant> {
         let threshold = Instant::now() - max_age;
+        let mut changed = false;
+        let mut next: Option<Instant> = None;
         self.last_pressed_map.retain(|key, instant| {
             let keep = *instant > threshold;
-            if !keep {
-                if let Some(thread) = self.input_update_thread.lock().deref() {
-                    thread.unpark();
-                }
+            if keep {
+                next = Some(next.map_or(*instant, |next| next.min(*instant)));
+            } else {
                 self.lit_keys_map[*key].store(false, Ordering::Release);
+                changed = true;
             }
             keep
         });
+
+        if changed {
+            self.update_paws();
+        }
+        next.map(|instant| instant + max_age)
+    }
+}
+
+/// Drops keystrokes older than a minute
+fn prune_recent(recent: &mut VecDeque<Instant>) {
+    if let Some(threshold) = Instant::now().checked_sub(Duration::from_secs(60)) {
+        while recent.front().is_some_and(|instant| *instant < threshold) {
+            recent.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_keys() {
+        use KeybdKey::*;
+        assert_eq!(KeyClass::of(AKey), KeyClass::Letter);
+        assert_eq!(KeyClass::of(ZKey), KeyClass::Letter);
+        assert_eq!(KeyClass::of(Numrow0Key), KeyClass::Digit);
+        assert_eq!(KeyClass::of(Numpad9Key), KeyClass::Digit);
+        assert_eq!(KeyClass::of(SlashKey), KeyClass::Symbol);
+        assert_eq!(KeyClass::of(BackquoteKey), KeyClass::Symbol);
+        assert_eq!(KeyClass::of(SpaceKey), KeyClass::Whitespace);
+        assert_eq!(KeyClass::of(BackspaceKey), KeyClass::Whitespace);
+        assert_eq!(KeyClass::of(LShiftKey), KeyClass::Modifier);
+        assert_eq!(KeyClass::of(CapsLockKey), KeyClass::Modifier);
+        assert_eq!(KeyClass::of(PageDownKey), KeyClass::Navigation);
+        assert_eq!(KeyClass::of(DeleteKey), KeyClass::Navigation);
+        assert_eq!(KeyClass::of(F12Key), KeyClass::Function);
+        assert_eq!(KeyClass::of(EscapeKey), KeyClass::Function);
+        assert_eq!(KeyClass::of(OtherKey(0x2a)), KeyClass::Other);
+    }
+
+    fn slaps(privacy: PrivacyMode, key: KeybdKey) -> Vec<KeystrokeEvent> {
+        let state = KeystrokeState {
+            privacy,
+            ..KeystrokeState::default()
+        };
+        let events = state.subscribe();
+        state.light(key);
+        events
+            .try_iter()
+            .filter(|event| matches!(event, KeystrokeEvent::Slap { .. }))
+            .collect()
+    }
+
+    fn paws(privacy: PrivacyMode, key: KeybdKey) -> Vec<KeystrokeEvent> {
+        let state = KeystrokeState {
+            privacy,
+            ..KeystrokeState::default()
+        };
+        let events = state.subscribe();
+        state.light(key);
+        events
+            .try_iter()
+            .filter(|event| matches!(event, KeystrokeEvent::Paws(_)))
+            .collect()
+    }
+
+    #[test]
+    fn paws_say_no_more_than_the_privacy_mode_allows() {
+        let right_o = PawState {
+            right: true,
+            o_face: true,
+            ..PawState::default()
+        };
+        assert_eq!(
+            paws(PrivacyMode::Off, KeybdKey::OKey),
+            [KeystrokeEvent::Paws(right_o)]
+        );
+        assert_eq!(
+            paws(PrivacyMode::Classes, KeybdKey::OKey),
+            [KeystrokeEvent::Paws(PawState {
+                o_face: false,
+                ..right_o
+            })]
+        );
+        let both = PawState {
+            left: true,
+            right: true,
+            o_face: false,
+        };
+        assert_eq!(
+            paws(PrivacyMode::Aggregate, KeybdKey::OKey),
+            [KeystrokeEvent::Paws(both)]
+        );
+        assert_eq!(
+            paws(PrivacyMode::Aggregate, KeybdKey::AKey),
+            [KeystrokeEvent::Paws(both)]
+        );
+    }
+
+    #[test]
+    fn slapping_while_paused_does_nothing() {
+        let state = KeystrokeState::default();
+        let events = state.subscribe();
+        state.set_paused_for(PauseReason::Locked, true);
+        state.slap(Paw::Both);
+
+        assert!(!state.lit_keys_map[KeybdKey::FKey].load(Ordering::Relaxed));
+        assert!(!state.lit_keys_map[KeybdKey::LKey].load(Ordering::Relaxed));
+        assert!(state.last_pressed_map.is_empty());
+        assert!(
+            events
+                .try_iter()
+                .all(|event| matches!(event, KeystrokeEvent::Paused { .. }))
+        );
+
+        state.set_paused_for(PauseReason::Locked, false);
+        state.slap(Paw::Both);
+        assert!(state.lit_keys_map[KeybdKey::FKey].load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn slaps_say_no_more_than_the_privacy_mode_allows() {
+        assert_eq!(
+            slaps(PrivacyMode::Off, KeybdKey::OKey),
+            [KeystrokeEvent::Slap {
+                paw: Paw::Right,
+                reaction: true,
+            }]
+        );
+        assert_eq!(
+            slaps(PrivacyMode::Classes, KeybdKey::OKey),
+            [KeystrokeEvent::Slap {
+                paw: Paw::Right,
+                reaction: false,
+            }]
+        );
+        assert_eq!(
+            slaps(PrivacyMode::Aggregate, KeybdKey::OKey),
+            [KeystrokeEvent::Slap {
+                paw: Paw::Both,
+                reaction: false,
+            }]
+        );
+        assert_eq!(
+            slaps(PrivacyMode::Aggregate, KeybdKey::AKey),
+            [KeystrokeEvent::Slap {
+                paw: Paw::Both,
+                reaction: false,
+            }]
+        );
+    }
+
+    /// Long enough that the test never runs into it
+    const MAX_AGE: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn cleanup_puts_out_stale_keys_and_returns_the_next_deadline() {
+        let state = KeystrokeState::default();
+        assert_eq!(state.cleanup_outdated(MAX_AGE), None);
+
+        let now = Instant::now();
+        let pressed = [
+            (KeybdKey::AKey, now - Duration::from_secs(5)),
+            (KeybdKey::BKey, now - Duration::from_secs(1)),
+            (KeybdKey::CKey, now - Duration::from_secs(20)),
+        ];
+        for (key, at) in pressed {
+            state.lit_keys_map[key].store(true, Ordering::Relaxed);
+            state.last_pressed_map.insert(key, at);
+        }
+
+        let deadline = state.cleanup_outdated(MAX_AGE);
+        assert_eq!(deadline, Some(pressed[0].1 + MAX_AGE));
+        assert!(state.lit_keys_map[KeybdKey::AKey].load(Ordering::Relaxed));
+        assert!(state.lit_keys_map[KeybdKey::BKey].load(Ordering::Relaxed));
+        assert!(!state.lit_keys_map[KeybdKey::CKey].load(Ordering::Relaxed));
+        assert!(!state.last_pressed_map.contains_key(&KeybdKey::CKey));
     }
 }
@@ -0,0 +1,270 @@
+//! Window-space geometry, derived from the base constants and the current scale
+use crate::consts::graphics::*;
+use crate::consts::keyboard::*;
+use crate::theme::{CounterStyle, KeyboardGeometry, ThemeManifest};
+use egui::{Pos2, Rect, Vec2};
+use std::f32::consts::{FRAC_PI_2, PI};
+
+/// Corners of each rounded key corner, not counting its ends
+const ROUNDING_SEGMENTS: usize = 4;
+
+/// A key's polygon, ready to be painted
+#[derive(Clone)]
+pub struct KeyShape {
+    pub key: &'static VisualKeybdKeyHolder,
+    /// Index of the key's row in `KEYS`
+    pub row: usize,
+    pub points: Vec<Pos2>,
+}
+
+/// Key polygons, ready to be painted
+pub type ShapeHolder = Vec<KeyShape>;
+
+/// What a theme changes about the key polygons
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ShapeParams {
+    pub geometry: KeyboardGeometry,
+    /// Radius of key corners, before scaling
+    pub rounding: f32,
+}
+
+impl ShapeParams {
+    #[must_use]
+    pub fn of(manifest: &ThemeManifest) -> Self {
+        Self {
+            geometry: manifest.keyboard,
+            rounding: manifest.overlay.rounding,
+        }
+    }
+}
+
+#[must_use]
+pub fn window_size(scale: f32) -> Vec2 {
+    Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT) * scale
+}
+
+#[must_use]
+pub fn window_rect(scale: f32) -> Rect {
+    Rect::from_min_size(Pos2::ZERO, window_size(scale))
+}
+
+/// Where the keystroke counter is anchored
+#[must_use]
+pub fn counter_position(style: &CounterStyle, scale: f32) -> Pos2 {
+    Pos2::new(style.x, style.y) * scale
+}
+
+/// Where the "Zzz" of the sleeping cat starts, above its head
+#[must_use]
+pub fn zzz_position(scale: f32) -> Pos2 {
+    Pos2::new(WINDOW_WIDTH * 0.62 * scale, WINDOW_HEIGHT * 0.3 * scale)
+}
+
+#[must_use]
+pub fn zzz_font_size(scale: f32) -> f32 {
+    WINDOW_HEIGHT / 12.5 * scale
+}
+
+fn rotate_point(center: Pos2, p: Pos2, theta: f32) -> Pos2 {
+    let x = p.x - center.x;
+    let y = p.y - center.y;
+    let cos_theta = theta.cos();
+    let sin_theta = theta.sin();
+    Pos2::new(
+        center.x + (x * cos_theta - y * sin_theta),
+        center.y + (x * sin_theta + y * cos_theta),
+    )
+}
+
+/// Outline of `rect`, with its corners rounded by up to `rounding`
+fn rounded_rect(rect: Rect, rounding: f32) -> Vec<Pos2> {
+    let corners = [
+        rect.left_top(),
+        rect.right_top(),
+        rect.right_bottom(),
+        rect.left_bottom(),
+    ];
+    let rounding = rounding.min(rect.width() / 2.0).min(rect.height() / 2.0);
+    if rounding <= 0.0 {
+        return corners.to_vec();
+    }
+
+    // Each corner is a quarter circle, starting where the previous side ends
+    let centers = [
+        rect.left_top() + Vec2::splat(rounding),
+        rect.right_top() + Vec2::new(-rounding, rounding),
+        rect.right_bottom() - Vec2::splat(rounding),
+        rect.left_bottom() + Vec2::new(rounding, -rounding),
+    ];
+    centers
+        .iter()
+        .enumerate()
+        .flat_map(|(i, center)| {
+            let start = PI + FRAC_PI_2 * i as f32;
+            (0..=ROUNDING_SEGMENTS).map(move |step| {
+                let angle = start + FRAC_PI_2 * step as f32 / ROUNDING_SEGMENTS as f32;
+                *center + Vec2::angled(angle) * rounding
+            })
+        })
+        .collect()
+}
+
+/// Lays out every key of the overlay where the theme puts the cat's keyboard
+#[must_use]
+pub fn shape_holder(scale: f32, params: &ShapeParams) -> ShapeHolder {
+    let geometry = &params.geometry;
+    let origin = Pos2::new(geometry.x, geometry.y);
+    let mut rects: Vec<(&'static VisualKeybdKeyHolder, usize, Rect)> = vec![];
+
+    let mut row_off = 0;
+    for y in (0..KEYS.len()).rev() {
+        let mut col_off = 0;
+        for x in (0..KEYS[y].len()).rev() {
+            let key = &KEYS[y][x];
+            let size_offset = key.size * 10.0;
+
+            rects.push((
+                key,
+                y,
+                Rect::from_min_size(
+                    origin + Vec2::new(col_off as f32, row_off as f32) * geometry.scale,
+                    Vec2::new(size_offset, PADDING_PIXELS as f32) * geometry.scale,
+                ),
+            ));
+
+            col_off += (PADDING_PIXELS as f32 * key.size) as u64;
+        }
+        row_off += PADDING_PIXELS;
+    }
+
+    // Calculate the keyboard center (e.g., average of key rect centers)
+    let all_rects: Vec<Rect> = rects.iter().map(|(_, _, r)| *r).collect();
+    let sum = all_rects.iter().fold(Pos2::ZERO, |s, r| {
+        let c = r.center();
+        Pos2::new(s.x + c.x, s.y + c.y)
+    });
+    let count = (all_rects.len() - 1) as f32;
+    let keyboard_center = if count > 0.0 {
+        Pos2::new(sum.x / count, sum.y / count)
+    } else {
+        Pos2::ZERO
+    };
+
+    rects
+        .into_iter()
+        .map(|(key, row, rect)| KeyShape {
+            key,
+            row,
+            points: rounded_rect(rect, params.rounding * geometry.scale)
+                .into_iter()
+                .map(|p| rotate_point(keyboard_center, p, geometry.angle.to_radians()))
+                .map(|p| Pos2::new(p.x * scale, p.y * scale))
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECT: Rect = Rect::from_min_max(Pos2::new(10.0, 20.0), Pos2::new(50.0, 40.0));
+
+    #[test]
+    fn square_corners_without_rounding() {
+        assert_eq!(
+            rounded_rect(RECT, 0.0),
+            [
+                RECT.left_top(),
+                RECT.right_top(),
+                RECT.right_bottom(),
+                RECT.left_bottom()
+            ]
+        );
+    }
+
+    #[test]
+    fn rounded_corners_stay_inside_the_rect() {
+        let points = rounded_rect(RECT, 5.0);
+        assert_eq!(points.len(), 4 * (ROUNDING_SEGMENTS + 1));
+        for point in &points {
+            assert!(RECT.expand(1e-3).contains(*point), "{point:?} is outside");
+        }
+        // The first corner starts on the left side and ends on the top
+        assert!((points[0] - Pos2::new(10.0, 25.0)).length() < 1e-3);
+        assert!((points[ROUNDING_SEGMENTS] - Pos2::new(15.0, 20.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn rounding_is_capped_at_half_the_shorter_side() {
+        let points = rounded_rect(RECT, 100.0);
+        // Half the height, so the left and right ends are semicircles
+        assert!((points[0] - Pos2::new(10.0, 30.0)).length() < 1e-3);
+        assert!((points[ROUNDING_SEGMENTS] - Pos2::new(20.0, 20.0)).length() < 1e-3);
+    }
+
+    /// Key corners as laid out before themes could place the keyboard
+    fn fixed_layout() -> Vec<Pos2> {
+        let mut rects = vec![];
+        let mut row_off = 0;
+        for row in KEYS.iter().rev() {
+            let mut col_off = 0;
+            for key in row.iter().rev() {
+                rects.push(Rect::from_min_max(
+                    Pos2::new(
+                        (4 + col_off) as f32,
+                        ((WINDOW_HEIGHT as u64 - 95) + row_off) as f32,
+                    ),
+                    Pos2::new(
+                        4.0 + key.size * 10.0 + col_off as f32,
+                        ((WINDOW_HEIGHT as u64 - 95 + PADDING_PIXELS) + row_off) as f32,
+                    ),
+                ));
+                col_off += (PADDING_PIXELS as f32 * key.size) as u64;
+            }
+            row_off += PADDING_PIXELS;
+        }
+
+        let sum = rects
+            .iter()
+            .fold(Vec2::ZERO, |sum, rect| sum + rect.center().to_vec2());
+        let center = (sum / (rects.len() - 1) as f32).to_pos2();
+        rects
+            .iter()
+            .flat_map(|rect| rounded_rect(*rect, 0.0))
+            .map(|p| rotate_point(center, p, CAT_ANGLE))
+            .collect()
+    }
+
+    #[test]
+    fn default_geometry_matches_the_fixed_layout() {
+        let params = ShapeParams {
+            rounding: 0.0,
+            ..ShapeParams::of(&ThemeManifest::default())
+        };
+        let points: Vec<Pos2> = shape_holder(1.0, &params)
+            .into_iter()
+            .flat_map(|shape| shape.points)
+            .collect();
+        let expected = fixed_layout();
+        assert_eq!(points.len(), expected.len());
+        for (point, expected) in points.iter().zip(&expected) {
+            assert!(
+                (*point - *expected).length() < 1e-3,
+                "{point:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn default_counter_stays_where_it_was() {
+        let position = counter_position(&CounterStyle::default(), 2.0);
+        assert_eq!(
+            position,
+            Pos2::new(
+                (WINDOW_WIDTH - WINDOW_WIDTH / 2.0) * 2.0,
+                (WINDOW_HEIGHT - (WINDOW_HEIGHT / 12.5) * 2.25) * 2.0,
+            )
+        );
+    }
+}
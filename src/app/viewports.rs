@@ -0,0 +1,197 @@
+//! Secondary windows opened from the tray menu
+use crate::app::BongoApp;
+use crate::app::command::AppCommand;
+use crate::app::keystroke::PauseReason;
+use crate::config::{APP_ID, Background, CounterMode, PrivacyMode};
+use crate::consts::graphics::*;
+use egui::{Context, ViewportBuilder, ViewportId};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+impl BongoApp {
+    pub(crate) fn show_stats(&mut self, ctx: &Context) {
+        if !self.stats_open {
+            return;
+        }
+
+        let state = self.keystroke_state.clone();
+        let close = ctx.show_viewport_immediate(
+            ViewportId::from_hash_of("stats"),
+            ViewportBuilder::default()
+                .with_title(format!("{APP_ID} stats"))
+                .with_inner_size([280.0, 360.0]),
+            |ctx, _class| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    egui::Grid::new("counts").num_columns(2).show(ui, |ui| {
+                        ui.label("Total keystrokes");
+                        ui.label(state.keystrokes.load(Ordering::Relaxed).to_string());
+                        ui.end_row();
+                        ui.label("This session");
+                        ui.label(state.session_keystrokes.load(Ordering::Relaxed).to_string());
+                        ui.end_row();
+                        ui.label("Keystrokes per minute");
+                        ui.label(state.keystrokes_per_minute().to_string());
+                        ui.end_row();
+                        ui.label("Uptime");
+                        ui.label(format_duration(state.uptime()));
+                        ui.end_row();
+                    });
+
+                    match state.privacy {
+                        PrivacyMode::Off => {
+                            ui.separator();
+                            ui.heading("Most pressed");
+                            egui::Grid::new("top_keys").num_columns(2).show(ui, |ui| {
+                                for (key, presses) in state.top_keys(10) {
+                                    ui.label(format!("{key:?}"));
+                                    ui.label(presses.to_string());
+                                    ui.end_row();
+                                }
+                            });
+                        }
+                        PrivacyMode::Classes => {
+                            ui.separator();
+                            ui.heading("By kind of key");
+                            egui::Grid::new("key_classes")
+                                .num_columns(2)
+                                .show(ui, |ui| {
+                                    for (class, presses) in state.key_classes() {
+                                        ui.label(format!("{class:?}"));
+                                        ui.label(presses.to_string());
+                                        ui.end_row();
+                                    }
+                                });
+                        }
+                        PrivacyMode::Aggregate => {}
+                    }
+                });
+
+                ctx.request_repaint_after(Duration::from_secs(1));
+                ctx.input(|i| i.viewport().close_requested())
+            },
+        );
+
+        if close {
+            self.stats_open = false;
+        }
+    }
+
+    pub(crate) fn show_settings(&mut self, ctx: &Context) {
+        if !self.settings_open {
+            return;
+        }
+
+        let theme_ids = self.themes.read().theme_ids();
+        let mut scale = self.scale();
+        let mut opacity = self.config.window.opacity;
+        let mut theme = self.theme.read().clone();
+        let mut counter = self.config.counter.show;
+        let mut background = self.config.window.background;
+        let mut visible = !self.hidden;
+        let mut edit_mode = self.edit_mode;
+        let mut paused = self.keystroke_state.is_paused_for(PauseReason::Manual);
+        #[cfg(feature = "audio")]
+        let (mut volume, mut muted) = (self.audio.volume(), self.audio.is_muted());
+        let mut reset_session = false;
+        let mut save_config = false;
+
+        let close = ctx.show_viewport_immediate(
+            ViewportId::from_hash_of("settings"),
+            ViewportBuilder::default()
+                .with_title(format!("{APP_ID} settings"))
+                .with_inner_size([320.0, 330.0]),
+            |ctx, _class| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.add(egui::Slider::new(&mut scale, MIN_SCALE..=MAX_SCALE).text("Scale"));
+                    ui.add(egui::Slider::new(&mut opacity, 0.0..=1.0).text("Opacity"));
+
+                    egui::ComboBox::from_label("Theme")
+                        .selected_text(theme.as_str())
+                        .show_ui(ui, |ui| {
+                            for id in &theme_ids {
+                                ui.selectable_value(&mut theme, id.to_string(), id.as_str());
+                            }
+                        });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Counter shows");
+                        ui.radio_value(&mut counter, CounterMode::Total, "total");
+                        ui.radio_value(&mut counter, CounterMode::Session, "session");
+                    });
+
+                    egui::ComboBox::from_label("Background")
+                        .selected_text(background.name())
+                        .show_ui(ui, |ui| {
+                            for option in Background::ALL {
+                                ui.selectable_value(&mut background, option, option.name());
+                            }
+                        });
+
+                    ui.checkbox(&mut visible, "Show the cat");
+                    ui.checkbox(&mut edit_mode, "Drag to move");
+                    ui.checkbox(&mut paused, "Pause counting");
+                    #[cfg(feature = "audio")]
+                    if self.config.audio.enabled {
+                        ui.add(egui::Slider::new(&mut volume, 0.0..=1.0).text("Volume"));
+                        ui.checkbox(&mut muted, "Mute sounds");
+                    }
+                    reset_session = ui.button("Reset session count").clicked();
+
+                    ui.separator();
+                    save_config = ui.button("Save to config file").clicked();
+                });
+
+                ctx.input(|i| i.viewport().close_requested())
+            },
+        );
+
+        if (scale - self.scale()).abs() > f32::EPSILON {
+            self.handle_command(ctx, AppCommand::SetScale(scale));
+        }
+        if theme != *self.theme.read() {
+            self.handle_command(ctx, AppCommand::SetTheme(theme));
+        }
+        if visible == self.hidden {
+            self.handle_command(ctx, AppCommand::ToggleVisibility);
+        }
+        if edit_mode != self.edit_mode {
+            self.handle_command(ctx, AppCommand::ToggleEditMode);
+        }
+        if paused != self.keystroke_state.is_paused_for(PauseReason::Manual) {
+            self.handle_command(ctx, AppCommand::TogglePause);
+        }
+        #[cfg(feature = "audio")]
+        {
+            self.audio.set_volume(volume);
+            self.audio.set_muted(muted);
+        }
+        if reset_session {
+            self.handle_command(ctx, AppCommand::ResetSession);
+        }
+        self.config.counter.show = counter;
+        self.config.window.opacity = opacity;
+        if background != self.config.window.background {
+            self.handle_command(ctx, AppCommand::SetBackground(background));
+        }
+
+        if save_config {
+            self.config.window.scale = scale;
+            #[cfg(feature = "audio")]
+            {
+                self.config.audio.volume = volume;
+            }
+            if let Err(e) = self.config.save() {
+                log::warn!("Failed to save config: {e}");
+            }
+        }
+
+        if close {
+            self.settings_open = false;
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Bongo sounds for every paw slap, from the active theme's `sounds` directory or built in
+use crate::app::keystroke::{KeystrokeEvent, KeystrokeState, Paw};
+use crate::config::AudioConfig;
+use crate::theme::AppTheme;
+use parking_lot::RwLock;
+use rodio::buffer::SamplesBuffer;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Source};
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 44_100;
+
+/// Volume and mute, shared with the UI
+#[derive(Debug)]
+pub struct Controls {
+    muted: AtomicBool,
+    /// `f32` bits
+    volume: AtomicU32,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self::new(&AudioConfig::default())
+    }
+}
+
+impl Controls {
+    #[must_use]
+    pub fn new(config: &AudioConfig) -> Self {
+        Self {
+            muted: AtomicBool::new(config.muted),
+            volume: AtomicU32::new(config.volume.clamp(0.0, 1.0).to_bits()),
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+
+    pub fn toggle_muted(&self) {
+        self.muted.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    pub fn set_muted(&self, muted: bool) {
+        self.muted.store(muted, Ordering::Relaxed);
+    }
+
+    pub fn volume(&self) -> f32 {
+        f32::from_bits(self.volume.load(Ordering::Relaxed))
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.volume
+            .store(volume.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// A sound ready to be played any number of times
+#[derive(Clone)]
+enum Sound {
+    /// Contents of an audio file, decoded on each play
+    File(Arc<[u8]>),
+    Samples(Arc<[f32]>),
+}
+
+impl Sound {
+    fn load(path: &Path) -> Option<Self> {
+        match std::fs::read(path) {
+            Ok(bytes) => Some(Self::File(bytes.into())),
+            Err(e) => {
+                log::warn!("Could not read sound {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    /// A drum hit: a sine sweeping down from `pitch` that dies out quickly
+    fn bongo(pitch: f32) -> Self {
+        let length = SAMPLE_RATE as usize / 5;
+        let samples = (0..length)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                let frequency = pitch * (1.0 + 0.5 * (-t * 40.0).exp());
+                (TAU * frequency * t).sin() * (-t * 25.0).exp()
+            })
+            .collect();
+        Self::Samples(samples)
+    }
+
+    fn play(&self, output: &OutputStreamHandle, volume: f32) {
+        let result = match self {
+            Self::File(bytes) => match Decoder::new(Cursor::new(bytes.clone())) {
+                Ok(decoder) => output.play_raw(decoder.convert_samples().amplify(volume)),
+                Err(e) => {
+                    log::warn!("Could not decode sound: {e}");
+                    return;
+                }
+            },
+            Self::Samples(samples) => output
+                .play_raw(SamplesBuffer::new(1, SAMPLE_RATE, samples.to_vec()).amplify(volume)),
+        };
+        if let Err(e) = result {
+            log::debug!("Could not play sound: {e}");
+        }
+    }
+}
+
+/// The sounds of one theme
+#[derive(Clone)]
+struct Sounds {
+    left: Sound,
+    right: Sound,
+    reaction: Option<Sound>,
+}
+
+impl Default for Sounds {
+    fn default() -> Self {
+        Self {
+            left: Sound::bongo(220.0),
+            right: Sound::bongo(165.0),
+            reaction: Some(Sound::bongo(330.0)),
+        }
+    }
+}
+
+impl Sounds {
+    /// The theme's own sounds, falling back to the built-in ones
+    fn new(theme: &AppTheme, fallback: &Self) -> Self {
+        let load = |path: &Option<PathBuf>| path.as_deref().and_then(Sound::load);
+        Self {
+            left: load(&theme.sounds.left).unwrap_or_else(|| fallback.left.clone()),
+            right: load(&theme.sounds.right).unwrap_or_else(|| fallback.right.clone()),
+            reaction: load(&theme.sounds.reaction).or_else(|| fallback.reaction.clone()),
+        }
+    }
+
+    fn get(&self, paw: Paw, reaction: bool) -> &Sound {
+        match (reaction, &self.reaction, paw) {
+            (true, Some(sound), _) => sound,
+            (_, _, Paw::Left) => &self.left,
+            _ => &self.right,
+        }
+    }
+}
+
+/// Plays a sound for every slap until `exit_notify` is set. Stays silent if there is no output device.
+pub fn play(
+    themes: &[AppTheme],
+    controls: Arc<Controls>,
+    keystroke_state: Arc<KeystrokeState>,
+    theme: Arc<RwLock<String>>,
+    exit_notify: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    let fallback = Sounds::default();
+    let sounds: HashMap<String, Sounds> = themes
+        .iter()
+        .map(|theme| (theme.id.to_string(), Sounds::new(theme, &fallback)))
+        .collect();
+    let events = keystroke_state.subscribe();
+
+    thread::spawn(move || {
+        // The stream has to stay on this thread and alive for as long as sounds play
+        let output = match OutputStream::try_default() {
+            Ok(output) => Some(output),
+            Err(e) => {
+                log::warn!("No audio output, staying silent: {e}");
+                None
+            }
+        };
+
+        loop {
+            match events.recv_timeout(Duration::from_secs(1)) {
+                Ok(KeystrokeEvent::Slap { paw, reaction }) => {
+                    if let Some((_, handle)) = &output
+                        && !controls.is_muted()
+                    {
+                        let sounds = sounds.get(theme.read().as_str()).unwrap_or(&fallback);
+                        sounds.get(paw, reaction).play(handle, controls.volume());
+                    }
+                }
+                Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+
+            if exit_notify.load(Ordering::Relaxed) {
+                return;
+            }
+        }
+    })
+}
@@ -0,0 +1,665 @@
+//! User configuration, read from `config.toml` in the app's storage directory
+use crate::app::command::AppCommand;
+use crate::hotkeys::{self, Hotkey, Modifiers};
+use crate::placement::{Corner, MonitorSelector};
+use crate::theme::HexColor;
+use egui::Color32;
+use inputbot::KeybdKey;
+use std::path::PathBuf;
+
+/// Name of the app, used for the window title and the storage directory
+pub const APP_ID: &str = "Bongocat";
+
+/// User configuration
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Window-related options
+    pub window: WindowConfig,
+    /// More cats, each in a window of its own
+    pub cats: Vec<CatConfig>,
+    /// Keystroke counter options
+    pub counter: CounterConfig,
+    /// What the cat does while nobody is typing
+    pub idle: IdleConfig,
+    /// Sound effects
+    pub audio: AudioConfig,
+    /// Visual effects
+    pub effects: EffectsConfig,
+    /// Global hotkey combos
+    pub hotkeys: HotkeyConfig,
+    /// System tray icon options
+    pub tray: TrayConfig,
+    /// Control socket options
+    pub ipc: IpcConfig,
+    /// Browser source overlay server options
+    pub overlay: OverlayConfig,
+    /// Prometheus metrics endpoint options
+    pub metrics: MetricsConfig,
+    /// Session bus interface options
+    pub dbus: DbusConfig,
+    /// What is kept about keystrokes, and when to stop counting them
+    pub privacy: PrivacyConfig,
+    /// Directory holding one subdirectory per custom theme
+    pub themes_dir: Option<PathBuf>,
+    /// Set when the file had settings that didn't parse, so saving won't overwrite what the user wrote
+    #[serde(skip)]
+    pub has_errors: bool,
+}
+
+impl Config {
+    /// Location of the config file, if a storage directory is available
+    #[must_use]
+    pub fn path() -> Option<PathBuf> {
+        eframe::storage_dir(APP_ID).map(|dir| dir.join("config.toml"))
+    }
+
+    /// Writes the config file, creating the storage directory if needed. Refuses to if the file had
+    /// errors when it was loaded, since the settings that didn't parse would be lost.
+    pub fn save(&self) -> std::io::Result<()> {
+        if self.has_errors {
+            return Err(std::io::Error::other(
+                "the config file has errors, fix them before saving over it",
+            ));
+        }
+        let path = Self::path().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "No storage directory")
+        })?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let contents = toml::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, contents)
+    }
+
+    /// Where custom themes are looked for
+    #[must_use]
+    pub fn themes_dir(&self) -> Option<PathBuf> {
+        self.themes_dir
+            .clone()
+            .or_else(|| eframe::storage_dir(APP_ID).map(|dir| dir.join("themes")))
+    }
+
+    /// Loads the config file, falling back to defaults when it is missing. Settings that don't parse
+    /// are reported on stderr and left at their defaults, keeping the rest.
+    #[must_use]
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                let (mut config, errors) = Self::parse(&contents);
+                for error in &errors {
+                    log::warn!("Ignoring invalid setting in {}: {error}", path.display());
+                }
+                config.has_errors = !errors.is_empty();
+                config
+            }
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Failed to read {}: {e}", path.display());
+                }
+                Self::default()
+            }
+        }
+    }
+
+    /// Parses `contents`, leaving settings that don't parse at their defaults. Returns what was wrong
+    /// with each of them.
+    #[must_use]
+    pub fn parse(contents: &str) -> (Self, Vec<String>) {
+        let mut table = match contents.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(e) => return (Self::default(), vec![e.to_string()]),
+        };
+        if let Ok(config) = Self::from_table(table.clone()) {
+            return (config, vec![]);
+        }
+
+        let mut errors = vec![];
+        drop_invalid(&mut table, &[], &mut errors);
+        match Self::from_table(table) {
+            Ok(config) => (config, errors),
+            Err(e) => {
+                errors.push(e.to_string());
+                (Self::default(), errors)
+            }
+        }
+    }
+
+    fn from_table(table: toml::Table) -> Result<Self, toml::de::Error> {
+        toml::Value::Table(table).try_into()
+    }
+}
+
+/// Removes every setting under `path` in `table` that keeps the config from parsing, going into
+/// sections so one bad setting doesn't take its neighbours with it
+fn drop_invalid(table: &mut toml::Table, path: &[&str], errors: &mut Vec<String>) {
+    let keys: Vec<String> = table.keys().cloned().collect();
+    for key in keys {
+        let value = table[&key].clone();
+        // Just this setting, nested back under its sections
+        let alone = path.iter().rev().fold(
+            toml::Table::from_iter([(key.clone(), value.clone())]),
+            |inner, section| toml::Table::from_iter([(section.to_string(), inner.into())]),
+        );
+        let Err(e) = Config::from_table(alone) else {
+            continue;
+        };
+
+        if let toml::Value::Table(mut section) = value {
+            let mut inner_path = path.to_vec();
+            inner_path.push(&key);
+            drop_invalid(&mut section, &inner_path, errors);
+            table.insert(key, section.into());
+        } else {
+            table.remove(&key);
+            errors.push(e.to_string());
+        }
+    }
+}
+
+/// Window-related options
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    /// Scale factor applied to the window and everything drawn in it
+    pub scale: f32,
+    /// Display to open on, by index or name
+    pub monitor: MonitorSelector,
+    /// Corner of the display to anchor to
+    pub corner: Corner,
+    /// Distance in pixels from the corner, horizontally then vertically
+    pub offset: [f32; 2],
+    /// Reopen wherever the window was last, as long as that's still on screen
+    pub remember_position: bool,
+    /// What is drawn behind the cat
+    pub background: Background,
+    /// Color of the `solid` background
+    pub background_color: HexColor,
+    /// What the cat does while a fullscreen window is focused on its display
+    pub over_fullscreen: OverFullscreen,
+    /// Opacity of the cat and keyboard overlay, from 0 to 1
+    pub opacity: f32,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            monitor: MonitorSelector::default(),
+            corner: Corner::default(),
+            offset: [0.0, 0.0],
+            remember_position: true,
+            background: Background::default(),
+            background_color: HexColor(Color32::BLACK),
+            over_fullscreen: OverFullscreen::default(),
+            opacity: 1.0,
+        }
+    }
+}
+
+/// Another cat, in a window of its own
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CatConfig {
+    /// Id of the theme to use, the main cat's if unset
+    pub theme: Option<String>,
+    /// Only react to keyboards whose name contains this, with counts of its own (Linux only).
+    /// Reacts to every keyboard along with the main cat if unset. The main cat always reacts to every keyboard.
+    pub device: Option<String>,
+    /// Scale, placement and opacity, like `window`
+    #[serde(flatten)]
+    pub window: WindowConfig,
+}
+
+/// What the cat does while a fullscreen window, like a game or video, is focused on its display
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverFullscreen {
+    /// Stay on top as usual
+    #[default]
+    Show,
+    Hide,
+    /// Stay on top, but barely visible
+    Fade,
+}
+
+/// What is drawn behind the cat. Capture software that can't handle transparent windows can key out a
+/// chroma color instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Background {
+    #[default]
+    Transparent,
+    /// `window.background_color`
+    Solid,
+    ChromaGreen,
+    ChromaMagenta,
+    /// The theme's `background.png`, transparent where it is
+    Image,
+}
+
+impl Background {
+    pub const ALL: [Self; 5] = [
+        Self::Transparent,
+        Self::Solid,
+        Self::ChromaGreen,
+        Self::ChromaMagenta,
+        Self::Image,
+    ];
+
+    /// The background after this one, wrapping around
+    #[must_use]
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    /// Color the window is cleared with, `solid` being `window.background_color`
+    #[must_use]
+    pub fn clear_color(self, solid: Color32) -> Color32 {
+        match self {
+            Self::Transparent | Self::Image => Color32::TRANSPARENT,
+            Self::Solid => solid,
+            Self::ChromaGreen => Color32::from_rgb(0, 255, 0),
+            Self::ChromaMagenta => Color32::from_rgb(255, 0, 255),
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Transparent => "Transparent",
+            Self::Solid => "Solid color",
+            Self::ChromaGreen => "Chroma green",
+            Self::ChromaMagenta => "Chroma magenta",
+            Self::Image => "Theme image",
+        }
+    }
+}
+
+/// Keystroke counter options
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CounterConfig {
+    /// Which count is shown
+    pub show: CounterMode,
+    /// TTF or OTF font file for the counter, used over the theme's
+    pub font: Option<PathBuf>,
+}
+
+/// What the cat does while nobody is typing
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct IdleConfig {
+    /// Whether to blink, yawn and sleep at all
+    pub enabled: bool,
+    /// Seconds without keystrokes before yawning
+    pub yawn_after_secs: u64,
+    /// Seconds without keystrokes before falling asleep
+    pub sleep_after_secs: u64,
+    /// Seconds without keystrokes before fading out, or 0 to never fade
+    pub fade_after_secs: u64,
+    /// Opacity once faded out, relative to `window.opacity`
+    pub faded_opacity: f32,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            yawn_after_secs: 60,
+            sleep_after_secs: 300,
+            fade_after_secs: 0,
+            faded_opacity: 0.3,
+        }
+    }
+}
+
+/// Visual effects, colored by the theme
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct EffectsConfig {
+    /// Fade pressed keys out instead of switching them off
+    pub fade: bool,
+    /// Burst a ripple out of every pressed key
+    pub ripples: bool,
+    /// Dip the cat on every slap and shake it on milestones
+    pub bounce: bool,
+    /// Enlarge the counter for a moment on milestones
+    pub counter_pop: bool,
+    /// Keystrokes between milestones, 0 for none
+    pub milestone: usize,
+}
+
+impl Default for EffectsConfig {
+    fn default() -> Self {
+        Self {
+            fade: true,
+            ripples: true,
+            bounce: true,
+            counter_pop: true,
+            milestone: 1000,
+        }
+    }
+}
+
+/// Sound effects, only played when built with the `audio` feature
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// Whether to play a sound for every paw slap
+    pub enabled: bool,
+    /// From 0 to 1
+    pub volume: f32,
+    /// Whether to start muted
+    pub muted: bool,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            volume: 0.5,
+            muted: false,
+        }
+    }
+}
+
+/// Which keystroke count the counter shows
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CounterMode {
+    /// Every keystroke ever
+    #[default]
+    Total,
+    /// Keystrokes since launch or the last reset
+    Session,
+}
+
+/// What is kept about keystrokes, and when to stop counting them
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    /// How much detail about which keys were pressed is kept
+    pub mode: PrivacyMode,
+    /// Pause counting while the focused window's class or title contains one of these, ignoring case
+    pub pause_in: Vec<String>,
+    /// Pause counting while the session is locked or the screensaver is on
+    pub pause_on_lock: bool,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            mode: PrivacyMode::Off,
+            pause_in: [
+                "password",
+                "passphrase",
+                "pinentry",
+                "polkit",
+                "gcr-prompter",
+                "ssh-askpass",
+                "keepassxc",
+                "bitwarden",
+                "1password",
+            ]
+            .map(String::from)
+            .to_vec(),
+            pause_on_lock: true,
+        }
+    }
+}
+
+/// How much detail about which keys were pressed is kept
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PrivacyMode {
+    /// Counts per key
+    #[default]
+    Off,
+    /// Counts per kind of key, like letters, digits or modifiers
+    Classes,
+    /// Only total counts
+    Aggregate,
+}
+
+/// System tray icon options
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TrayConfig {
+    /// Whether to show the tray icon
+    pub enabled: bool,
+}
+
+impl Default for TrayConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Control socket options
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct IpcConfig {
+    /// Whether to listen for `bongocat ctl` and other scripts
+    pub enabled: bool,
+    /// Where the socket goes, instead of `$XDG_RUNTIME_DIR/bongocat.sock`
+    pub socket_path: Option<PathBuf>,
+}
+
+impl Default for IpcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            socket_path: None,
+        }
+    }
+}
+
+/// Browser source overlay server options
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct OverlayConfig {
+    /// Whether to serve the overlay page and its WebSocket feed
+    pub enabled: bool,
+    /// Address to listen on
+    pub address: String,
+    /// Whether `key-pressed` events say which key it was. Anything that can reach the feed can read
+    /// them, so they're left out unless asked for.
+    pub send_keys: bool,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:7878".to_string(),
+            send_keys: false,
+        }
+    }
+}
+
+/// Prometheus metrics endpoint options
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Whether to serve `/metrics`
+    pub enabled: bool,
+    /// Address to listen on
+    pub address: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:9898".to_string(),
+        }
+    }
+}
+
+/// Session bus interface options, only used on Linux
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DbusConfig {
+    /// Whether to publish the cat on the session bus
+    pub enabled: bool,
+    /// Emit a `Milestone` signal every this many keystrokes, 0 for never
+    pub milestone: usize,
+}
+
+impl Default for DbusConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            milestone: 1000,
+        }
+    }
+}
+
+/// Global hotkey combos, `"none"` unbinds one
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct HotkeyConfig {
+    /// Makes the window bigger
+    #[serde(with = "hotkeys::optional")]
+    pub scale_up: Option<Hotkey>,
+    /// Makes the window smaller
+    #[serde(with = "hotkeys::optional")]
+    pub scale_down: Option<Hotkey>,
+    /// Toggles edit mode, where the window can be dragged around
+    #[serde(with = "hotkeys::optional")]
+    pub toggle_edit_mode: Option<Hotkey>,
+    /// Shows or hides the cat
+    #[serde(with = "hotkeys::optional")]
+    pub toggle_visibility: Option<Hotkey>,
+    /// Switches to the next theme
+    #[serde(with = "hotkeys::optional")]
+    pub cycle_theme: Option<Hotkey>,
+    /// Sets the session keystroke count back to zero
+    #[serde(with = "hotkeys::optional")]
+    pub reset_session: Option<Hotkey>,
+    /// Stops or resumes counting keystrokes
+    #[serde(with = "hotkeys::optional")]
+    pub toggle_pause: Option<Hotkey>,
+    /// Mutes or unmutes sound effects
+    #[serde(with = "hotkeys::optional")]
+    pub toggle_mute: Option<Hotkey>,
+    /// Switches to the next background
+    #[serde(with = "hotkeys::optional")]
+    pub cycle_background: Option<Hotkey>,
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        let ctrl_alt = Modifiers {
+            ctrl: true,
+            alt: true,
+            ..Default::default()
+        };
+
+        Self {
+            scale_up: Some(Hotkey::new(ctrl_alt, KeybdKey::EqualKey)),
+            scale_down: Some(Hotkey::new(ctrl_alt, KeybdKey::MinusKey)),
+            toggle_edit_mode: Some(Hotkey::new(ctrl_alt, KeybdKey::MKey)),
+            toggle_visibility: Some(Hotkey::new(ctrl_alt, KeybdKey::HKey)),
+            cycle_theme: Some(Hotkey::new(ctrl_alt, KeybdKey::TKey)),
+            reset_session: Some(Hotkey::new(ctrl_alt, KeybdKey::RKey)),
+            toggle_pause: Some(Hotkey::new(ctrl_alt, KeybdKey::PKey)),
+            toggle_mute: Some(Hotkey::new(ctrl_alt, KeybdKey::SKey)),
+            cycle_background: Some(Hotkey::new(ctrl_alt, KeybdKey::BKey)),
+        }
+    }
+}
+
+impl HotkeyConfig {
+    /// Every bound hotkey along with the command it sends
+    #[must_use]
+    pub fn bindings(&self) -> Vec<(Hotkey, AppCommand)> {
+        [
+            (self.scale_up, AppCommand::ScaleUp),
+            (self.scale_down, AppCommand::ScaleDown),
+            (self.toggle_edit_mode, AppCommand::ToggleEditMode),
+            (self.toggle_visibility, AppCommand::ToggleVisibility),
+            (self.cycle_theme, AppCommand::CycleTheme),
+            (self.reset_session, AppCommand::ResetSession),
+            (self.toggle_pause, AppCommand::TogglePause),
+            (self.toggle_mute, AppCommand::ToggleMute),
+            (self.cycle_background, AppCommand::CycleBackground),
+        ]
+        .into_iter()
+        .filter_map(|(hotkey, command)| hotkey.map(|hotkey| (hotkey, command)))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_through_every_background() {
+        let mut background = Background::default();
+        let mut seen = vec![];
+        for _ in 0..Background::ALL.len() {
+            seen.push(background);
+            background = background.next();
+        }
+        assert_eq!(seen, Background::ALL);
+        assert_eq!(background, Background::default());
+    }
+
+    #[test]
+    fn only_solid_uses_the_background_color() {
+        let solid = Color32::from_rgb(1, 2, 3);
+        assert_eq!(Background::Solid.clear_color(solid), solid);
+        assert_eq!(
+            Background::Transparent.clear_color(solid),
+            Color32::TRANSPARENT
+        );
+        assert_eq!(Background::Image.clear_color(solid), Color32::TRANSPARENT);
+        assert_eq!(
+            Background::ChromaGreen.clear_color(solid),
+            Color32::from_rgb(0, 255, 0)
+        );
+    }
+
+    #[test]
+    fn parses_valid_configs_without_errors() {
+        let (config, errors) = Config::parse("[privacy]\nmode = \"classes\"\n");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(config.privacy.mode, PrivacyMode::Classes);
+    }
+
+    #[test]
+    fn keeps_the_settings_that_parse() {
+        let (config, errors) = Config::parse(
+            r#"
+            [window]
+            scale = 2.0
+
+            [hotkeys]
+            scale_up = "Ctrl+NotAKey"
+            scale_down = "none"
+
+            [privacy]
+            mode = "aggregate"
+            "#,
+        );
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(config.window.scale, 2.0);
+        assert_eq!(config.hotkeys.scale_up, HotkeyConfig::default().scale_up);
+        assert_eq!(config.hotkeys.scale_down, None);
+        assert_eq!(config.privacy.mode, PrivacyMode::Aggregate);
+    }
+
+    #[test]
+    fn reports_broken_files() {
+        let (config, errors) = Config::parse("[privacy\nmode = ");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(config.privacy.mode, PrivacyMode::default());
+    }
+}
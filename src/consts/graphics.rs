@@ -8,3 +8,8 @@ pub const WINDOW_RECT: egui::Rect = egui::Rect::from_min_max(
 pub const UV_RECT: egui::Rect =
     egui::Rect::from_min_max(egui::Pos2::ZERO, egui::Pos2::new(1.0, 1.0));
 pub const CAT_ANGLE: f32 = 0.231_605_19;
+pub const MIN_SCALE: f32 = 0.5;
+pub const MAX_SCALE: f32 = 4.0;
+pub const SCALE_STEP: f32 = 0.25;
+/// Opacity of the cat over fullscreen windows when `over_fullscreen` is `fade`
+pub const FULLSCREEN_OPACITY: f32 = 0.2;
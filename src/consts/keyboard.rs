@@ -2,7 +2,10 @@ use inputbot::KeybdKey;
 
 pub const KEY_PRESSED_LIT_DELAY: std::time::Duration = std::time::Duration::from_millis(250);
 
-pub const KEY_PRESSED_CLEANUP_DELAY: std::time::Duration = std::time::Duration::from_millis(33);
+/// `OtherKey` code of the blank gaps between keys, which aren't drawn
+pub const SPACER: u64 = u64::MAX - 1;
+/// `OtherKey` code of keys that are drawn but never lit, like Print Screen and Pause
+pub const UNMAPPED: u64 = u64::MAX - 2;
 
 pub struct VisualKeybdKeyHolder {
     pub size: f32,
@@ -11,6 +14,20 @@ pub struct VisualKeybdKeyHolder {
     pub column: u32,
 }
 
+impl VisualKeybdKeyHolder {
+    /// Whether this is a gap between keys rather than a key
+    #[must_use]
+    pub const fn is_spacer(&self) -> bool {
+        matches!(self.key, KeybdKey::OtherKey(SPACER))
+    }
+
+    /// Whether this is a real key that lights up when pressed
+    #[must_use]
+    pub const fn is_mapped(&self) -> bool {
+        !matches!(self.key, KeybdKey::OtherKey(SPACER | UNMAPPED))
+    }
+}
+
 pub const KEYS: [&[VisualKeybdKeyHolder]; 6] = [
     &ROW_ONE, &ROW_TWO, &ROW_THREE, &ROW_FOUR, &ROW_FIVE, &ROW_SIX,
 ];
@@ -24,7 +41,7 @@ const ROW_ONE: [VisualKeybdKeyHolder; 21] = [
     },
     VisualKeybdKeyHolder {
         size: 1.0,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 1,
     },
@@ -54,7 +71,7 @@ const ROW_ONE: [VisualKeybdKeyHolder; 21] = [
     },
     VisualKeybdKeyHolder {
         size: 0.5,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 6,
     },
@@ -84,7 +101,7 @@ const ROW_ONE: [VisualKeybdKeyHolder; 21] = [
     },
     VisualKeybdKeyHolder {
         size: 0.5,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 11,
     },
@@ -114,19 +131,19 @@ const ROW_ONE: [VisualKeybdKeyHolder; 21] = [
     },
     VisualKeybdKeyHolder {
         size: 0.5,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 16,
     },
     VisualKeybdKeyHolder {
         size: 1.0,
-        key: KeybdKey::OtherKey(u64::MAX - 1 - 1),
+        key: KeybdKey::OtherKey(UNMAPPED),
         name: "Prt.Scn",
         column: 17,
     },
     VisualKeybdKeyHolder {
         size: 1.0,
-        key: KeybdKey::OtherKey(u64::MAX - 1 - 1),
+        key: KeybdKey::OtherKey(UNMAPPED),
         name: "Pause",
         column: 18,
     },
@@ -138,7 +155,7 @@ const ROW_ONE: [VisualKeybdKeyHolder; 21] = [
     },
     VisualKeybdKeyHolder {
         size: 4.5,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 20,
     },
@@ -230,7 +247,7 @@ const ROW_TWO: [VisualKeybdKeyHolder; 23] = [
     },
     VisualKeybdKeyHolder {
         size: 0.5,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 14,
     },
@@ -254,7 +271,7 @@ const ROW_TWO: [VisualKeybdKeyHolder; 23] = [
     },
     VisualKeybdKeyHolder {
         size: 0.5,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 18,
     },
@@ -370,7 +387,7 @@ const ROW_THREE: [VisualKeybdKeyHolder; 23] = [
     },
     VisualKeybdKeyHolder {
         size: 0.5,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 14,
     },
@@ -394,7 +411,7 @@ const ROW_THREE: [VisualKeybdKeyHolder; 23] = [
     },
     VisualKeybdKeyHolder {
         size: 0.5,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 18,
     },
@@ -504,7 +521,7 @@ const ROW_FOUR: [VisualKeybdKeyHolder; 18] = [
     },
     VisualKeybdKeyHolder {
         size: 4.0,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 13,
     },
@@ -608,7 +625,7 @@ const ROW_FIVE: [VisualKeybdKeyHolder; 19] = [
     },
     VisualKeybdKeyHolder {
         size: 1.5,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 12,
     },
@@ -620,7 +637,7 @@ const ROW_FIVE: [VisualKeybdKeyHolder; 19] = [
     },
     VisualKeybdKeyHolder {
         size: 1.5,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 14,
     },
@@ -694,7 +711,7 @@ const ROW_SIX: [VisualKeybdKeyHolder; 15] = [
     },
     VisualKeybdKeyHolder {
         size: 0.5,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 7,
     },
@@ -718,7 +735,7 @@ const ROW_SIX: [VisualKeybdKeyHolder; 15] = [
     },
     VisualKeybdKeyHolder {
         size: 0.5,
-        key: KeybdKey::OtherKey(u64::MAX - 1),
+        key: KeybdKey::OtherKey(SPACER),
         name: " ",
         column: 11,
     },
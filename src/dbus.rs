@@ -0,0 +1,369 @@
+//! Session bus object so desktop widgets, status bars and scripts can watch and control the cat
+//!
+//! Try it with `busctl --user introspect io.github.CanadianBaconBoi.Bongocat /io/github/CanadianBaconBoi/Bongocat`.
+use crate::app::keystroke::{KeystrokeEvent, Paw};
+use crate::config::DbusConfig;
+use crate::ipc::{Handles, Request, Response};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use zbus::blocking::connection;
+use zbus::blocking::object_server::InterfaceRef;
+use zbus::object_server::SignalEmitter;
+use zbus::{fdo, interface};
+
+pub const BUS_NAME: &str = "io.github.CanadianBaconBoi.Bongocat";
+pub const OBJECT_PATH: &str = "/io/github/CanadianBaconBoi/Bongocat";
+
+struct Bongocat {
+    handles: Handles,
+}
+
+impl Bongocat {
+    fn request(&self, request: Request) -> fdo::Result<()> {
+        match self.handles.handle(request) {
+            Response::Error { message } => Err(fdo::Error::Failed(message)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[interface(name = "io.github.CanadianBaconBoi.Bongocat1")]
+impl Bongocat {
+    /// Number of total keystrokes ever
+    #[zbus(property)]
+    fn keystrokes(&self) -> u64 {
+        self.handles
+            .keystroke_state
+            .keystrokes
+            .load(Ordering::Relaxed) as u64
+    }
+
+    /// Number of keystrokes since launch or the last reset
+    #[zbus(property)]
+    fn session_keystrokes(&self) -> u64 {
+        self.handles
+            .keystroke_state
+            .session_keystrokes
+            .load(Ordering::Relaxed) as u64
+    }
+
+    /// Id of the active theme
+    #[zbus(property)]
+    fn theme(&self) -> String {
+        self.handles.theme.read().clone()
+    }
+
+    /// Whether keystrokes are currently ignored
+    #[zbus(property)]
+    fn paused(&self) -> bool {
+        self.handles.keystroke_state.is_paused()
+    }
+
+    fn set_theme(&self, theme: String) -> fdo::Result<()> {
+        self.request(Request::SetTheme { theme })
+    }
+
+    fn pause(&self) -> fdo::Result<()> {
+        self.request(Request::Pause)
+    }
+
+    fn resume(&self) -> fdo::Result<()> {
+        self.request(Request::Resume)
+    }
+
+    fn reset_session(&self) -> fdo::Result<()> {
+        self.request(Request::Reset)
+    }
+
+    /// `paw` is `left`, `right` or `both`
+    fn slap(&self, paw: &str) -> fdo::Result<()> {
+        let paw = <Paw as clap::ValueEnum>::from_str(paw, true).map_err(fdo::Error::InvalidArgs)?;
+        self.request(Request::Slap { paw })
+    }
+
+    /// A counter reached a multiple of the configured milestone; `counter` is `total` or `session`
+    #[zbus(signal)]
+    async fn milestone(
+        emitter: &SignalEmitter<'_>,
+        counter: &str,
+        keystrokes: u64,
+    ) -> zbus::Result<()>;
+}
+
+/// Property values as of the last change signals
+#[derive(Default, PartialEq)]
+struct Published {
+    keystrokes: usize,
+    session_keystrokes: usize,
+    theme: String,
+    paused: bool,
+}
+
+impl Published {
+    fn new(handles: &Handles) -> Self {
+        Self {
+            keystrokes: handles.keystroke_state.keystrokes.load(Ordering::Relaxed),
+            session_keystrokes: handles
+                .keystroke_state
+                .session_keystrokes
+                .load(Ordering::Relaxed),
+            theme: handles.theme.read().clone(),
+            paused: handles.keystroke_state.is_paused(),
+        }
+    }
+
+    /// Sends `PropertiesChanged` for every property that differs from `last`
+    fn notify(&self, last: &Self, iface: &InterfaceRef<Bongocat>) -> zbus::Result<()> {
+        let emitter = iface.signal_emitter();
+        let bongocat = iface.get();
+        zbus::block_on(async {
+            if self.keystrokes != last.keystrokes {
+                bongocat.keystrokes_changed(emitter).await?;
+            }
+            if self.session_keystrokes != last.session_keystrokes {
+                bongocat.session_keystrokes_changed(emitter).await?;
+            }
+            if self.theme != last.theme {
+                bongocat.theme_changed(emitter).await?;
+            }
+            if self.paused != last.paused {
+                bongocat.paused_changed(emitter).await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Emits `Milestone` for each counter that just landed on a multiple of `interval`
+fn milestones(
+    iface: &InterfaceRef<Bongocat>,
+    interval: usize,
+    keystrokes: usize,
+    session_keystrokes: usize,
+) -> zbus::Result<()> {
+    if interval == 0 {
+        return Ok(());
+    }
+
+    let emitter = iface.signal_emitter();
+    zbus::block_on(async {
+        if keystrokes % interval == 0 {
+            Bongocat::milestone(emitter, "total", keystrokes as u64).await?;
+        }
+        if session_keystrokes % interval == 0 {
+            Bongocat::milestone(emitter, "session", session_keystrokes as u64).await?;
+        }
+        Ok(())
+    })
+}
+
+/// Claims the bus name and publishes changes until `exit_notify` is set
+pub fn serve(
+    config: &DbusConfig,
+    handles: Handles,
+    exit_notify: Arc<AtomicBool>,
+) -> zbus::Result<JoinHandle<()>> {
+    serve_on(
+        connection::Builder::session()?,
+        config,
+        handles,
+        exit_notify,
+    )
+}
+
+/// Like [`serve`], on whichever bus `builder` connects to
+fn serve_on(
+    builder: connection::Builder<'_>,
+    config: &DbusConfig,
+    handles: Handles,
+    exit_notify: Arc<AtomicBool>,
+) -> zbus::Result<JoinHandle<()>> {
+    let events = handles.keystroke_state.subscribe();
+    let connection = builder
+        .name(BUS_NAME)?
+        .serve_at(
+            OBJECT_PATH,
+            Bongocat {
+                handles: handles.clone(),
+            },
+        )?
+        .build()?;
+    let iface = connection
+        .object_server()
+        .interface::<_, Bongocat>(OBJECT_PATH)?;
+    let interval = config.milestone;
+
+    Ok(thread::spawn(move || {
+        let mut published = Published::new(&handles);
+
+        // Every property change comes with an event; the timeout is only there to notice exit
+        loop {
+            let result = match events.recv_timeout(Duration::from_secs(1)) {
+                Ok(KeystrokeEvent::KeyPressed {
+                    keystrokes,
+                    session_keystrokes,
+                    ..
+                }) => milestones(&iface, interval, keystrokes, session_keystrokes),
+                Ok(
+                    KeystrokeEvent::Paws(_)
+                    | KeystrokeEvent::Slap { .. }
+                    | KeystrokeEvent::Paused { .. }
+                    | KeystrokeEvent::SessionReset
+                    | KeystrokeEvent::Theme { .. },
+                ) => Ok(()),
+                Err(RecvTimeoutError::Timeout) => {
+                    if exit_notify.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if let Err(e) = result {
+                log::warn!("Failed to emit D-Bus signal: {e}");
+            }
+
+            let latest = Published::new(&handles);
+            if latest != published {
+                if let Err(e) = latest.notify(&published, &iface) {
+                    log::warn!("Failed to emit D-Bus signal: {e}");
+                }
+                published = latest;
+            }
+
+            if exit_notify.load(Ordering::Relaxed) {
+                break;
+            }
+        }
+
+        drop(connection);
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::command::AppCommand;
+    use crate::theme::ThemeSet;
+    use egui::Context;
+    use parking_lot::RwLock;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Command, Stdio};
+    use std::sync::mpsc::channel;
+    use zbus::blocking::{Proxy, fdo::PropertiesProxy, proxy};
+    use zbus::proxy::CacheProperties;
+
+    const INTERFACE: &str = "io.github.CanadianBaconBoi.Bongocat1";
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn publishes_changes_on_a_private_bus() {
+        let Ok(mut daemon) = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+        else {
+            eprintln!("dbus-daemon is not installed, skipping");
+            return;
+        };
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        let address = address.trim().to_string();
+
+        // Stand in for the app, which applies theme switches on the UI thread
+        let (commands, received) = channel();
+        let handles = Handles {
+            keystroke_state: Arc::default(),
+            theme: Arc::new(RwLock::new("standard".to_string())),
+            themes: Arc::new(RwLock::new(ThemeSet {
+                ids: vec![
+                    Arc::new("standard".to_string()),
+                    Arc::new("other".to_string()),
+                ],
+                ..ThemeSet::default()
+            })),
+            commands,
+            context: Context::default(),
+        };
+        {
+            let keystroke_state = handles.keystroke_state.clone();
+            let theme = handles.theme.clone();
+            thread::spawn(move || {
+                while let Ok(command) = received.recv() {
+                    if let AppCommand::SetTheme(id) = command {
+                        *theme.write() = id.clone();
+                        keystroke_state.announce_theme(&id);
+                    }
+                }
+            });
+        }
+
+        let exit_notify = Arc::new(AtomicBool::new(false));
+        let server = serve_on(
+            connection::Builder::address(address.as_str()).unwrap(),
+            &DbusConfig::default(),
+            handles.clone(),
+            exit_notify.clone(),
+        )
+        .unwrap();
+
+        let client = connection::Builder::address(address.as_str())
+            .unwrap()
+            .build()
+            .unwrap();
+        let (changed, changes) = channel();
+        let (ready, listening) = channel();
+        {
+            let client = client.clone();
+            thread::spawn(move || {
+                let properties = PropertiesProxy::builder(&client)
+                    .destination(BUS_NAME)
+                    .unwrap()
+                    .path(OBJECT_PATH)
+                    .unwrap()
+                    .build()
+                    .unwrap();
+                let signals = properties.receive_properties_changed().unwrap();
+                ready.send(()).unwrap();
+                for signal in signals {
+                    let args = signal.args().unwrap();
+                    for name in args.changed_properties().keys() {
+                        let _ = changed.send(name.to_string());
+                    }
+                }
+            });
+        }
+        listening.recv_timeout(TIMEOUT).unwrap();
+
+        let proxy: Proxy = proxy::Builder::new(&client)
+            .destination(BUS_NAME)
+            .unwrap()
+            .path(OBJECT_PATH)
+            .unwrap()
+            .interface(INTERFACE)
+            .unwrap()
+            .cache_properties(CacheProperties::No)
+            .build()
+            .unwrap();
+
+        let _: () = proxy.call("Pause", &()).unwrap();
+        assert_eq!(changes.recv_timeout(TIMEOUT).unwrap(), "Paused");
+        assert!(proxy.get_property::<bool>("Paused").unwrap());
+
+        let _: () = proxy.call("SetTheme", &("other",)).unwrap();
+        assert_eq!(changes.recv_timeout(TIMEOUT).unwrap(), "Theme");
+        assert_eq!(proxy.get_property::<String>("Theme").unwrap(), "other");
+
+        assert!(proxy.call::<_, _, ()>("SetTheme", &("missing",)).is_err());
+        assert_eq!(proxy.get_property::<String>("Theme").unwrap(), "other");
+
+        exit_notify.store(true, Ordering::Relaxed);
+        server.join().unwrap();
+        let _ = daemon.kill();
+    }
+}
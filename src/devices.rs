@@ -0,0 +1,214 @@
+//! Reads keyboards straight from `/dev/input`, so a cat can react to one keyboard only
+use crate::app::command::AppCommand;
+use crate::app::keystroke::KeystrokeState;
+use crate::hotkeys::Hotkey;
+use inputbot::KeybdKey;
+use std::ffi::c_long;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+/// Size of `struct input_event`: a `timeval`, then the type, code and value
+const EVENT_SIZE: usize = 2 * size_of::<c_long>() + 8;
+/// Event type of key presses and releases
+const EV_KEY: u16 = 1;
+/// Event value of a key going down
+const KEY_DOWN: i32 = 1;
+
+/// Input devices whose name contains `filter`, ignoring case, along with their names
+#[must_use]
+pub fn find(filter: &str) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/input") else {
+        return vec![];
+    };
+    let filter = filter.to_lowercase();
+
+    let mut devices: Vec<(String, PathBuf)> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("event"))
+        .filter_map(|entry| {
+            let name = std::fs::read_to_string(entry.path().join("device/name")).ok()?;
+            let name = name.trim().to_string();
+            name.to_lowercase()
+                .contains(&filter)
+                .then(|| (name, PathBuf::from("/dev/input").join(entry.file_name())))
+        })
+        .collect();
+    devices.sort_by(|a, b| a.1.cmp(&b.1));
+    devices
+}
+
+/// Counts key presses from every device matching `filter` into `keystroke_state`, skipping them while
+/// `pauses` is paused and leaving out keys that finish one of `hotkeys`. Returns how many devices are
+/// being read.
+///
+/// Reads block until the next key, so the threads are left running until the process exits.
+pub fn capture(
+    filter: &str,
+    keystroke_state: Arc<KeystrokeState>,
+    pauses: Arc<KeystrokeState>,
+    hotkeys: Arc<Vec<(Hotkey, AppCommand)>>,
+) -> usize {
+    let mut capturing = 0;
+    for (name, path) in find(filter) {
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                log::warn!("Could not read {name} at {}: {e}", path.display());
+                continue;
+            }
+        };
+        log::info!("Reading keys from {name} at {}", path.display());
+
+        let keystroke_state = keystroke_state.clone();
+        let pauses = pauses.clone();
+        let hotkeys = hotkeys.clone();
+        thread::spawn(move || {
+            let mut event = [0; EVENT_SIZE];
+            while file.read_exact(&mut event).is_ok() {
+                let [kind, code] = [0, 2].map(|i| {
+                    let at = EVENT_SIZE - 8 + i;
+                    u16::from_ne_bytes([event[at], event[at + 1]])
+                });
+                let value = i32::from_ne_bytes(event[EVENT_SIZE - 4..].try_into().unwrap());
+                if kind != EV_KEY || value != KEY_DOWN || pauses.is_paused() {
+                    continue;
+                }
+                // The main cat's listener sends the hotkey's command
+                if let Some(key) = key(code)
+                    && !hotkeys.iter().any(|(hotkey, _)| hotkey.matches(key))
+                {
+                    keystroke_state.log_keystroke(&key);
+                }
+            }
+            log::warn!("Stopped reading keys from {name}");
+        });
+        capturing += 1;
+    }
+    capturing
+}
+
+/// The key drawn on the keyboard for evdev key `code`
+fn key(code: u16) -> Option<KeybdKey> {
+    use KeybdKey::*;
+    Some(match code {
+        1 => EscapeKey,
+        2 => Numrow1Key,
+        3 => Numrow2Key,
+        4 => Numrow3Key,
+        5 => Numrow4Key,
+        6 => Numrow5Key,
+        7 => Numrow6Key,
+        8 => Numrow7Key,
+        9 => Numrow8Key,
+        10 => Numrow9Key,
+        11 => Numrow0Key,
+        12 => MinusKey,
+        13 => EqualKey,
+        14 => BackspaceKey,
+        15 => TabKey,
+        16 => QKey,
+        17 => WKey,
+        18 => EKey,
+        19 => RKey,
+        20 => TKey,
+        21 => YKey,
+        22 => UKey,
+        23 => IKey,
+        24 => OKey,
+        25 => PKey,
+        26 => LBracketKey,
+        27 => RBracketKey,
+        28 => EnterKey,
+        29 => LControlKey,
+        30 => AKey,
+        31 => SKey,
+        32 => DKey,
+        33 => FKey,
+        34 => GKey,
+        35 => HKey,
+        36 => JKey,
+        37 => KKey,
+        38 => LKey,
+        39 => SemicolonKey,
+        40 => QuoteKey,
+        41 => BackquoteKey,
+        42 => LShiftKey,
+        43 => BackslashKey,
+        44 => ZKey,
+        45 => XKey,
+        46 => CKey,
+        47 => VKey,
+        48 => BKey,
+        49 => NKey,
+        50 => MKey,
+        51 => CommaKey,
+        52 => PeriodKey,
+        53 => SlashKey,
+        54 => RShiftKey,
+        // Numpad operators and enter are `OtherKey`s on the drawn keyboard
+        55 => OtherKey(63),
+        56 => LAltKey,
+        57 => SpaceKey,
+        58 => CapsLockKey,
+        59 => F1Key,
+        60 => F2Key,
+        61 => F3Key,
+        62 => F4Key,
+        63 => F5Key,
+        64 => F6Key,
+        65 => F7Key,
+        66 => F8Key,
+        67 => F9Key,
+        68 => F10Key,
+        69 => NumLockKey,
+        70 => ScrollLockKey,
+        71 => Numpad7Key,
+        72 => Numpad8Key,
+        73 => Numpad9Key,
+        74 => OtherKey(109),
+        75 => Numpad4Key,
+        76 => Numpad5Key,
+        77 => Numpad6Key,
+        78 => OtherKey(69),
+        79 => Numpad1Key,
+        80 => Numpad2Key,
+        81 => Numpad3Key,
+        82 => Numpad0Key,
+        83 => OtherKey(83),
+        87 => F11Key,
+        88 => F12Key,
+        96 => OtherKey(96),
+        97 => RControlKey,
+        98 => OtherKey(61),
+        100 => RAltKey,
+        102 => HomeKey,
+        103 => UpKey,
+        104 => PageUpKey,
+        105 => LeftKey,
+        106 => RightKey,
+        107 => EndKey,
+        108 => DownKey,
+        109 => PageDownKey,
+        110 => InsertKey,
+        111 => DeleteKey,
+        125 => LSuper,
+        126 => RSuper,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keypad_period_is_not_delete() {
+        assert_eq!(key(83), Some(KeybdKey::OtherKey(83)));
+        assert_eq!(key(111), Some(KeybdKey::DeleteKey));
+        assert_eq!(key(71), Some(KeybdKey::Numpad7Key));
+        assert_eq!(key(0), None);
+    }
+}
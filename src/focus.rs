@@ -0,0 +1,337 @@
+//! Pauses counting while sensitive windows, like password prompts, are focused
+use crate::app::keystroke::{KeystrokeState, PauseReason};
+use crate::config::PrivacyConfig;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+#[cfg(target_os = "linux")]
+use std::sync::atomic::Ordering;
+#[cfg(target_os = "linux")]
+use std::thread;
+use std::thread::JoinHandle;
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+/// How often the focused window is checked
+#[cfg(target_os = "linux")]
+const POLL_DELAY: Duration = Duration::from_millis(250);
+
+/// The focused window's class and title
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FocusedWindow {
+    pub class: String,
+    pub title: String,
+}
+
+impl FocusedWindow {
+    /// Whether the class or title contains one of `patterns`, ignoring case
+    #[must_use]
+    pub fn matches(&self, patterns: &[String]) -> bool {
+        let class = self.class.to_lowercase();
+        let title = self.title.to_lowercase();
+        patterns.iter().any(|pattern| {
+            let pattern = pattern.to_lowercase();
+            class.contains(&pattern) || title.contains(&pattern)
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) mod x11 {
+    use super::FocusedWindow;
+    use egui::{Pos2, Rect, Vec2};
+    use std::error::Error;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{Atom, AtomEnum, ConnectionExt, Window};
+    use x11rb::rust_connection::RustConnection;
+
+    /// Asks the X server (or XWayland) which window is active
+    pub struct Watcher {
+        connection: RustConnection,
+        root: Window,
+        active_window: Atom,
+        wm_name: Atom,
+        utf8_string: Atom,
+        wm_state: Atom,
+        wm_state_fullscreen: Atom,
+    }
+
+    impl Watcher {
+        pub fn new() -> Result<Self, Box<dyn Error>> {
+            Self::connect(None)
+        }
+
+        /// Watches `display`, or `$DISPLAY` if it's `None`
+        pub fn connect(display: Option<&str>) -> Result<Self, Box<dyn Error>> {
+            let (connection, screen) = x11rb::connect(display)?;
+            let root = connection.setup().roots[screen].root;
+            let intern = |name: &[u8]| -> Result<Atom, Box<dyn Error>> {
+                Ok(connection.intern_atom(false, name)?.reply()?.atom)
+            };
+            let active_window = intern(b"_NET_ACTIVE_WINDOW")?;
+            let wm_name = intern(b"_NET_WM_NAME")?;
+            let utf8_string = intern(b"UTF8_STRING")?;
+            let wm_state = intern(b"_NET_WM_STATE")?;
+            let wm_state_fullscreen = intern(b"_NET_WM_STATE_FULLSCREEN")?;
+
+            Ok(Self {
+                connection,
+                root,
+                active_window,
+                wm_name,
+                utf8_string,
+                wm_state,
+                wm_state_fullscreen,
+            })
+        }
+
+        fn property(
+            &self,
+            window: Window,
+            property: impl Into<Atom>,
+            kind: impl Into<Atom>,
+        ) -> Result<Vec<u8>, Box<dyn Error>> {
+            Ok(self
+                .connection
+                .get_property(false, window, property, kind, 0, 1024)?
+                .reply()?
+                .value)
+        }
+
+        fn active_window(&self) -> Result<Option<Window>, Box<dyn Error>> {
+            Ok(self
+                .connection
+                .get_property(false, self.root, self.active_window, AtomEnum::WINDOW, 0, 1)?
+                .reply()?
+                .value32()
+                .and_then(|mut values| values.next())
+                .filter(|window| *window != 0))
+        }
+
+        pub fn focused(&self) -> Result<Option<FocusedWindow>, Box<dyn Error>> {
+            let Some(window) = self.active_window()? else {
+                return Ok(None);
+            };
+
+            // WM_CLASS holds the instance and class names, each null-terminated
+            let class = self.property(window, AtomEnum::WM_CLASS, AtomEnum::STRING)?;
+            let mut title = self.property(window, self.wm_name, self.utf8_string)?;
+            if title.is_empty() {
+                title = self.property(window, AtomEnum::WM_NAME, AtomEnum::STRING)?;
+            }
+
+            Ok(Some(FocusedWindow {
+                class: String::from_utf8_lossy(&class).replace('\0', " "),
+                title: String::from_utf8_lossy(&title).into_owned(),
+            }))
+        }
+
+        /// Where the focused window is on screen, in pixels, if it is fullscreen
+        pub fn fullscreen(&self) -> Result<Option<Rect>, Box<dyn Error>> {
+            let Some(window) = self.active_window()? else {
+                return Ok(None);
+            };
+
+            let fullscreen = self
+                .connection
+                .get_property(false, window, self.wm_state, AtomEnum::ATOM, 0, 32)?
+                .reply()?
+                .value32()
+                .is_some_and(|mut states| states.any(|state| state == self.wm_state_fullscreen));
+            if !fullscreen {
+                return Ok(None);
+            }
+
+            let geometry = self.connection.get_geometry(window)?.reply()?;
+            let origin = self
+                .connection
+                .translate_coordinates(window, self.root, 0, 0)?
+                .reply()?;
+            Ok(Some(Rect::from_min_size(
+                Pos2::new(origin.dst_x.into(), origin.dst_y.into()),
+                Vec2::new(geometry.width.into(), geometry.height.into()),
+            )))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::process::{Child, Command, Stdio};
+        use std::thread;
+        use std::time::Duration;
+        use x11rb::protocol::xproto::{CreateWindowAux, PropMode, WindowClass};
+        use x11rb::wrapper::ConnectionExt as _;
+
+        /// A private X server, killed when dropped
+        struct Xvfb {
+            server: Child,
+            display: String,
+        }
+
+        impl Drop for Xvfb {
+            fn drop(&mut self) {
+                let _ = self.server.kill();
+                let _ = self.server.wait();
+            }
+        }
+
+        /// Starts Xvfb on a display of its own for each `test`, and waits for it to take connections.
+        /// `None` if Xvfb isn't installed.
+        fn xvfb(test: u32) -> Option<Xvfb> {
+            let display = format!(":{}", 100 + std::process::id() % 400 * 2 + test);
+            let Ok(server) = Command::new("Xvfb")
+                .args([&display, "-nolisten", "tcp"])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+            else {
+                eprintln!("Xvfb is not installed, skipping");
+                return None;
+            };
+            let xvfb = Xvfb { server, display };
+            for _ in 0..50 {
+                if x11rb::connect(Some(&xvfb.display)).is_ok() {
+                    return Some(xvfb);
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            panic!("Xvfb did not start on {}", xvfb.display);
+        }
+
+        /// Creates a window at `rect` and makes it the active one, like a window manager would
+        fn active_window(watcher: &Watcher, rect: Rect) -> Window {
+            let connection = &watcher.connection;
+            let window = connection.generate_id().unwrap();
+            connection
+                .create_window(
+                    x11rb::COPY_DEPTH_FROM_PARENT,
+                    window,
+                    watcher.root,
+                    rect.min.x as i16,
+                    rect.min.y as i16,
+                    rect.width() as u16,
+                    rect.height() as u16,
+                    0,
+                    WindowClass::INPUT_OUTPUT,
+                    x11rb::COPY_FROM_PARENT,
+                    &CreateWindowAux::new(),
+                )
+                .unwrap();
+            connection
+                .change_property32(
+                    PropMode::REPLACE,
+                    watcher.root,
+                    watcher.active_window,
+                    AtomEnum::WINDOW,
+                    &[window],
+                )
+                .unwrap();
+            connection.sync().unwrap();
+            window
+        }
+
+        fn set_states(watcher: &Watcher, window: Window, states: &[Atom]) {
+            watcher
+                .connection
+                .change_property32(
+                    PropMode::REPLACE,
+                    window,
+                    watcher.wm_state,
+                    AtomEnum::ATOM,
+                    states,
+                )
+                .unwrap();
+            watcher.connection.sync().unwrap();
+        }
+
+        #[test]
+        fn finds_the_fullscreen_window() {
+            let Some(xvfb) = xvfb(0) else {
+                return;
+            };
+            let watcher = Watcher::connect(Some(&xvfb.display)).unwrap();
+            assert_eq!(watcher.fullscreen().unwrap(), None);
+
+            let rect = Rect::from_min_size(Pos2::new(10.0, 20.0), Vec2::new(300.0, 200.0));
+            let window = active_window(&watcher, rect);
+            set_states(&watcher, window, &[watcher.wm_state_fullscreen]);
+            assert_eq!(watcher.fullscreen().unwrap(), Some(rect));
+        }
+
+        #[test]
+        fn ignores_windows_that_are_not_fullscreen() {
+            let Some(xvfb) = xvfb(1) else {
+                return;
+            };
+            let watcher = Watcher::connect(Some(&xvfb.display)).unwrap();
+            let above = watcher
+                .connection
+                .intern_atom(false, b"_NET_WM_STATE_ABOVE")
+                .unwrap()
+                .reply()
+                .unwrap()
+                .atom;
+
+            let rect = Rect::from_min_size(Pos2::new(0.0, 0.0), Vec2::new(640.0, 480.0));
+            let window = active_window(&watcher, rect);
+            assert_eq!(watcher.fullscreen().unwrap(), None);
+
+            set_states(&watcher, window, &[above]);
+            assert_eq!(watcher.fullscreen().unwrap(), None);
+        }
+    }
+}
+
+/// Keeps `PauseReason::FocusedApp` in sync with the focused window until `exit_notify` is set
+#[cfg(target_os = "linux")]
+pub fn watch(
+    config: &PrivacyConfig,
+    keystroke_state: Arc<KeystrokeState>,
+    exit_notify: Arc<AtomicBool>,
+) -> Option<JoinHandle<()>> {
+    if config.pause_in.is_empty() {
+        return None;
+    }
+
+    let watcher = match x11::Watcher::new() {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            log::warn!("Can't watch the focused window, so apps won't pause counting: {e}");
+            return None;
+        }
+    };
+    let patterns = config.pause_in.clone();
+
+    Some(thread::spawn(move || {
+        while !exit_notify.load(Ordering::Relaxed) {
+            let sensitive = match watcher.focused() {
+                Ok(window) => window.is_some_and(|window| window.matches(&patterns)),
+                Err(e) => {
+                    log::debug!("Could not get the focused window: {e}");
+                    false
+                }
+            };
+            if sensitive != keystroke_state.is_paused_for(PauseReason::FocusedApp) {
+                log::info!(
+                    "{} counting for the focused window",
+                    if sensitive { "Pausing" } else { "Resuming" }
+                );
+                keystroke_state.set_paused_for(PauseReason::FocusedApp, sensitive);
+            }
+            thread::sleep(POLL_DELAY);
+        }
+    }))
+}
+
+/// Keeps `PauseReason::FocusedApp` in sync with the focused window until `exit_notify` is set
+#[cfg(not(target_os = "linux"))]
+pub fn watch(
+    config: &PrivacyConfig,
+    _keystroke_state: Arc<KeystrokeState>,
+    _exit_notify: Arc<AtomicBool>,
+) -> Option<JoinHandle<()>> {
+    if !config.pause_in.is_empty() {
+        log::debug!("Pausing for focused apps is only supported on Linux");
+    }
+    None
+}
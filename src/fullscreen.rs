@@ -0,0 +1,69 @@
+//! Notices fullscreen windows, like games and video players, so the cat can get out of their way
+#[cfg(target_os = "linux")]
+use crate::focus::x11;
+use egui::{Context, Rect};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+#[cfg(target_os = "linux")]
+use std::sync::atomic::Ordering;
+#[cfg(target_os = "linux")]
+use std::thread;
+use std::thread::JoinHandle;
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+/// How often the focused window is checked
+#[cfg(target_os = "linux")]
+const POLL_DELAY: Duration = Duration::from_millis(500);
+
+/// Where the focused fullscreen window is, in pixels, if there is one
+pub type FullscreenWindow = Arc<Mutex<Option<Rect>>>;
+
+/// Keeps `fullscreen` in sync with the focused window until `exit_notify` is set, repainting `ctx`
+/// whenever it changes
+#[cfg(target_os = "linux")]
+pub fn watch(
+    ctx: Context,
+    fullscreen: FullscreenWindow,
+    exit_notify: Arc<AtomicBool>,
+) -> Option<JoinHandle<()>> {
+    let watcher = match x11::Watcher::new() {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            log::warn!("Can't watch for fullscreen windows, so the cat won't hide for them: {e}");
+            return None;
+        }
+    };
+
+    Some(thread::spawn(move || {
+        while !exit_notify.load(Ordering::Relaxed) {
+            let rect = watcher.fullscreen().unwrap_or_else(|e| {
+                log::debug!("Could not check for a fullscreen window: {e}");
+                None
+            });
+            let changed = {
+                let mut fullscreen = fullscreen.lock();
+                let changed = *fullscreen != rect;
+                *fullscreen = rect;
+                changed
+            };
+            if changed {
+                ctx.request_repaint();
+            }
+            thread::sleep(POLL_DELAY);
+        }
+    }))
+}
+
+/// Keeps `fullscreen` in sync with the focused window until `exit_notify` is set, repainting `ctx`
+/// whenever it changes
+#[cfg(not(target_os = "linux"))]
+pub fn watch(
+    _ctx: Context,
+    _fullscreen: FullscreenWindow,
+    _exit_notify: Arc<AtomicBool>,
+) -> Option<JoinHandle<()>> {
+    log::debug!("Hiding over fullscreen windows is only supported on Linux");
+    None
+}
@@ -0,0 +1,233 @@
+//! Global hotkey combos, matched from the same input callbacks used for keystrokes
+use inputbot::KeybdKey;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Modifier keys that have to be held for a hotkey to fire
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub super_key: bool,
+}
+
+impl Modifiers {
+    /// The modifiers currently held down
+    #[must_use]
+    pub fn pressed() -> Self {
+        Self {
+            ctrl: KeybdKey::LControlKey.is_pressed() || KeybdKey::RControlKey.is_pressed(),
+            alt: KeybdKey::LAltKey.is_pressed() || KeybdKey::RAltKey.is_pressed(),
+            shift: KeybdKey::LShiftKey.is_pressed() || KeybdKey::RShiftKey.is_pressed(),
+            super_key: KeybdKey::LSuper.is_pressed() || KeybdKey::RSuper.is_pressed(),
+        }
+    }
+}
+
+/// A key plus the exact set of modifiers it has to be pressed with
+///
+/// Written as `+`-separated modifiers followed by an `inputbot` key name, e.g. `Ctrl+Alt+EqualKey`.
+/// Only keys shown on the keyboard overlay are listened for, so only those can be used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Hotkey {
+    pub modifiers: Modifiers,
+    pub key: KeybdKey,
+}
+
+impl Hotkey {
+    #[must_use]
+    pub const fn new(modifiers: Modifiers, key: KeybdKey) -> Self {
+        Self { modifiers, key }
+    }
+
+    /// Whether `key` being pressed right now triggers this hotkey
+    #[must_use]
+    pub fn matches(&self, key: KeybdKey) -> bool {
+        self.matches_with(Modifiers::pressed(), key)
+    }
+
+    /// Whether `key` being pressed while holding exactly `held` triggers this hotkey
+    #[must_use]
+    pub fn matches_with(&self, held: Modifiers, key: KeybdKey) -> bool {
+        self.key == key && self.modifiers == held
+    }
+}
+
+impl FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Modifiers::default();
+        let mut parts = s.split('+').map(str::trim).peekable();
+        let mut key = None;
+
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                key = Some(
+                    KeybdKey::deserialize(serde::de::value::StrDeserializer::<
+                        serde::de::value::Error,
+                    >::new(part))
+                    .map_err(|e| format!("Unknown key `{part}` in hotkey `{s}`: {e}"))?,
+                );
+                break;
+            }
+
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "super" | "win" | "meta" => modifiers.super_key = true,
+                _ => return Err(format!("Unknown modifier `{part}` in hotkey `{s}`")),
+            }
+        }
+
+        key.map(|key| Self { modifiers, key })
+            .ok_or_else(|| format!("Hotkey `{s}` has no key"))
+    }
+}
+
+impl TryFrom<String> for Hotkey {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Hotkey> for String {
+    fn from(value: Hotkey) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.ctrl {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.alt {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.shift {
+            f.write_str("Shift+")?;
+        }
+        if self.modifiers.super_key {
+            f.write_str("Super+")?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+/// (De)serializes an `Option<Hotkey>`, where `"none"` means unbound
+pub mod optional {
+    use super::Hotkey;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Hotkey>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(hotkey) => serializer.serialize_str(&hotkey.to_string()),
+            None => serializer.serialize_str("none"),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Hotkey>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        if value.is_empty() || value.eq_ignore_ascii_case("none") {
+            Ok(None)
+        } else {
+            value.parse().map(Some).map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_modifiers_and_key() {
+        let hotkey: Hotkey = "Ctrl+Alt+EqualKey".parse().unwrap();
+        let modifiers = Modifiers {
+            ctrl: true,
+            alt: true,
+            ..Modifiers::default()
+        };
+        assert_eq!(hotkey, Hotkey::new(modifiers, KeybdKey::EqualKey));
+    }
+
+    #[test]
+    fn modifier_names_ignore_case_and_spaces() {
+        let hotkey: Hotkey = " control + SHIFT + win + MKey".parse().unwrap();
+        let modifiers = Modifiers {
+            ctrl: true,
+            shift: true,
+            super_key: true,
+            ..Modifiers::default()
+        };
+        assert_eq!(hotkey, Hotkey::new(modifiers, KeybdKey::MKey));
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        for text in ["Ctrl+Alt+MKey", "Shift+Super+F1Key", "TKey"] {
+            assert_eq!(text.parse::<Hotkey>().unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_modifiers_and_keys() {
+        assert!("Hyper+MKey".parse::<Hotkey>().is_err());
+        assert!("Ctrl+NotAKey".parse::<Hotkey>().is_err());
+        assert!("".parse::<Hotkey>().is_err());
+    }
+
+    #[test]
+    fn chords_need_exactly_their_modifiers() {
+        let hotkey: Hotkey = "Ctrl+Shift+PKey".parse().unwrap();
+        let ctrl_shift = Modifiers {
+            ctrl: true,
+            shift: true,
+            ..Modifiers::default()
+        };
+        assert!(hotkey.matches_with(ctrl_shift, KeybdKey::PKey));
+        assert!(!hotkey.matches_with(ctrl_shift, KeybdKey::OKey));
+        assert!(!hotkey.matches_with(
+            Modifiers {
+                ctrl: true,
+                ..Modifiers::default()
+            },
+            KeybdKey::PKey
+        ));
+        assert!(!hotkey.matches_with(
+            Modifiers {
+                alt: true,
+                ..ctrl_shift
+            },
+            KeybdKey::PKey
+        ));
+        // The modifiers alone are typing until the final key completes the chord
+        assert!(!hotkey.matches_with(ctrl_shift, KeybdKey::LShiftKey));
+    }
+
+    #[test]
+    fn none_unbinds() {
+        #[derive(serde::Deserialize)]
+        struct Bindings {
+            #[serde(with = "optional")]
+            hotkey: Option<Hotkey>,
+        }
+
+        let unbound: Bindings = toml::from_str(r#"hotkey = "none""#).unwrap();
+        assert_eq!(unbound.hotkey, None);
+        let bound: Bindings = toml::from_str(r#"hotkey = "Alt+HKey""#).unwrap();
+        assert_eq!(bound.hotkey, Some("Alt+HKey".parse().unwrap()));
+    }
+}
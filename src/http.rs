@@ -0,0 +1,217 @@
+//! Just enough HTTP/1.1 for the local overlay and metrics servers
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+/// The parts of a request the servers care about
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Value of the header `name`, compared case-insensitively
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Reads a request line and headers, ignoring any body
+    pub fn read(stream: &TcpStream) -> io::Result<Self> {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Malformed request line",
+            ));
+        };
+        let method = method.to_string();
+        let path = path.to_string();
+
+        let mut headers = vec![];
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((key, value)) = header.split_once(':') {
+                headers.push((key.trim().to_string(), value.trim().to_string()));
+            }
+        }
+
+        Ok(Self {
+            method,
+            path,
+            headers,
+        })
+    }
+}
+
+/// Writes a complete response and closes the connection
+pub fn respond(
+    mut stream: &TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+pub fn not_found(stream: &TcpStream) -> io::Result<()> {
+    respond(stream, "404 Not Found", "text/plain", b"Not found")
+}
+
+/// `Host`s the server answers to for `address`. Any other name pointing at it is some other site
+/// getting around the browser's same-origin checks through DNS rebinding.
+fn own_hosts(address: &str) -> Vec<String> {
+    let mut hosts = vec![address.to_string()];
+    if let Some((_, port)) = address.rsplit_once(':') {
+        hosts.extend(["localhost", "127.0.0.1", "[::1]"].map(|host| format!("{host}:{port}")));
+    }
+    hosts
+}
+
+/// Whether `request` is addressed to one of `hosts` and, when it comes from a web page, from one of
+/// their pages. Other pages open in the browser could otherwise read what's served.
+fn is_allowed(hosts: &[String], request: &Request) -> bool {
+    let is_own = |host: &str| hosts.iter().any(|own| own.eq_ignore_ascii_case(host));
+    request.header("Host").is_some_and(is_own)
+        && request
+            .header("Origin")
+            .is_none_or(|origin| origin.strip_prefix("http://").is_some_and(is_own))
+}
+
+/// Accepts connections on `address`, handing each one to `handler` on its own thread, until
+/// `exit_notify` is set and `wake` is called. Requests for other hosts or from other sites are refused.
+pub fn serve<F>(
+    address: &str,
+    handler: F,
+    exit_notify: Arc<AtomicBool>,
+) -> io::Result<(SocketAddr, JoinHandle<()>)>
+where
+    F: Fn(TcpStream, Request) -> io::Result<()> + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(address)?;
+    let mut hosts = own_hosts(address);
+    let address = listener.local_addr()?;
+    hosts.extend(own_hosts(&address.to_string()));
+    let hosts = Arc::new(hosts);
+    let handler = Arc::new(handler);
+
+    let handle = thread::spawn(move || {
+        for stream in listener.incoming() {
+            if exit_notify.load(Ordering::Relaxed) {
+                break;
+            }
+
+            match stream {
+                Ok(stream) => {
+                    let (handler, hosts) = (handler.clone(), hosts.clone());
+                    thread::spawn(move || {
+                        let result = Request::read(&stream).and_then(|req| {
+                            if is_allowed(&hosts, &req) {
+                                handler(stream, req)
+                            } else {
+                                respond(&stream, "403 Forbidden", "text/plain", b"")
+                            }
+                        });
+                        if let Err(e) = result {
+                            log::debug!("HTTP connection closed: {e}");
+                        }
+                    });
+                }
+                Err(e) => log::warn!("Failed to accept HTTP connection: {e}"),
+            }
+        }
+    });
+
+    Ok((address, handle))
+}
+
+/// Unblocks a server thread so it can notice it should exit
+pub fn wake(address: SocketAddr) {
+    let _ = TcpStream::connect(address);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(headers: &[(&str, &str)]) -> Request {
+        Request {
+            method: "GET".to_string(),
+            path: "/ws".to_string(),
+            headers: headers
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn answers_own_page_and_non_browser_clients() {
+        let hosts = own_hosts("127.0.0.1:7878");
+        for host in [
+            "127.0.0.1:7878",
+            "localhost:7878",
+            "LOCALHOST:7878",
+            "[::1]:7878",
+        ] {
+            assert!(is_allowed(&hosts, &request(&[("Host", host)])));
+        }
+        assert!(is_allowed(
+            &hosts,
+            &request(&[
+                ("Host", "localhost:7878"),
+                ("Origin", "http://127.0.0.1:7878")
+            ])
+        ));
+    }
+
+    #[test]
+    fn refuses_other_sites() {
+        let hosts = own_hosts("127.0.0.1:7878");
+        assert!(!is_allowed(
+            &hosts,
+            &request(&[
+                ("Host", "127.0.0.1:7878"),
+                ("Origin", "https://example.com")
+            ])
+        ));
+        assert!(!is_allowed(
+            &hosts,
+            &request(&[("Host", "127.0.0.1:7878"), ("Origin", "null")])
+        ));
+        // DNS rebinding: a page on another name that now resolves to us
+        assert!(!is_allowed(
+            &hosts,
+            &request(&[
+                ("Host", "evil.example:7878"),
+                ("Origin", "http://evil.example:7878")
+            ])
+        ));
+        assert!(!is_allowed(&hosts, &request(&[("Host", "127.0.0.1:9999")])));
+        assert!(!is_allowed(&hosts, &request(&[])));
+    }
+}
@@ -0,0 +1,251 @@
+//! Local control socket for scripting the running app, plus the client side used by `bongocat ctl`
+//!
+//! The protocol is one JSON `Request` per line, each answered by one JSON `Response` line.
+use crate::app::command::AppCommand;
+use crate::app::keystroke::{KeystrokeState, Paw};
+use crate::config::IpcConfig;
+use crate::theme::ThemeSet;
+use egui::Context;
+use parking_lot::RwLock;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+
+/// Something asked of the running app
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Request {
+    /// Get the current `Stats`
+    Stats,
+    /// Switch to the theme with this id
+    SetTheme { theme: String },
+    /// Stop counting keystrokes
+    Pause,
+    /// Start counting keystrokes again
+    Resume,
+    /// Set the session keystroke count back to zero
+    Reset,
+    /// Make the cat slap without counting a keystroke
+    Slap { paw: Paw },
+    /// Close the app
+    Quit,
+}
+
+/// The running app's answer to a `Request`
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum Response {
+    Ok,
+    Stats(Stats),
+    Error { message: String },
+}
+
+/// Snapshot of the app's counters
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Stats {
+    pub keystrokes: usize,
+    pub session_keystrokes: usize,
+    pub keystrokes_per_minute: usize,
+    pub uptime_secs: u64,
+    pub paused: bool,
+    pub theme: String,
+}
+
+impl Stats {
+    #[must_use]
+    pub fn new(state: &KeystrokeState, theme: &str) -> Self {
+        Self {
+            keystrokes: state.keystrokes.load(Ordering::Relaxed),
+            session_keystrokes: state.session_keystrokes.load(Ordering::Relaxed),
+            keystrokes_per_minute: state.keystrokes_per_minute(),
+            uptime_secs: state.uptime().as_secs(),
+            paused: state.is_paused(),
+            theme: theme.to_string(),
+        }
+    }
+}
+
+/// Where the socket lives: the configured path, or `bongocat.sock` in the runtime directory
+#[must_use]
+pub fn socket_path(config: &IpcConfig) -> PathBuf {
+    config.socket_path.clone().unwrap_or_else(|| {
+        std::env::var_os("XDG_RUNTIME_DIR")
+            .map_or_else(std::env::temp_dir, PathBuf::from)
+            .join("bongocat.sock")
+    })
+}
+
+/// Everything a connection needs to answer requests
+#[derive(Clone)]
+pub struct Handles {
+    pub keystroke_state: Arc<KeystrokeState>,
+    pub theme: Arc<RwLock<String>>,
+    /// Themes `SetTheme` can pick from
+    pub themes: Arc<RwLock<ThemeSet>>,
+    pub commands: Sender<AppCommand>,
+    pub context: Context,
+}
+
+impl Handles {
+    pub(crate) fn handle(&self, request: Request) -> Response {
+        let command = match request {
+            Request::Stats => {
+                return Response::Stats(Stats::new(&self.keystroke_state, &self.theme.read()));
+            }
+            Request::SetTheme { theme } => {
+                if !self
+                    .themes
+                    .read()
+                    .theme_ids()
+                    .iter()
+                    .any(|id| **id == theme)
+                {
+                    return Response::Error {
+                        message: format!("Unknown theme `{theme}`"),
+                    };
+                }
+                AppCommand::SetTheme(theme)
+            }
+            Request::Pause => {
+                self.keystroke_state.set_paused(true);
+                return Response::Ok;
+            }
+            Request::Resume => {
+                self.keystroke_state.set_paused(false);
+                return Response::Ok;
+            }
+            Request::Reset => AppCommand::ResetSession,
+            Request::Slap { paw } => {
+                self.keystroke_state.slap(paw);
+                self.context.request_repaint();
+                return Response::Ok;
+            }
+            Request::Quit => AppCommand::Quit,
+        };
+
+        let _ = self.commands.send(command);
+        self.context.request_repaint();
+        Response::Ok
+    }
+
+    fn serve_connection(&self, stream: UnixStream) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str(&line) {
+                Ok(request) => self.handle(request),
+                Err(e) => Response::Error {
+                    message: format!("Invalid request: {e}"),
+                },
+            };
+            serde_json::to_writer(&mut writer, &response)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Binds the socket, replacing a stale one left behind by a crash
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is already in use by another instance", path.display()),
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    UnixListener::bind(path)
+}
+
+/// Starts answering requests on `path` until `exit_notify` is set and `wake` is called
+pub fn serve(
+    path: PathBuf,
+    handles: Handles,
+    exit_notify: Arc<AtomicBool>,
+) -> io::Result<JoinHandle<()>> {
+    let listener = bind(&path)?;
+
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            if exit_notify.load(Ordering::Relaxed) {
+                break;
+            }
+
+            match stream {
+                Ok(stream) => {
+                    let handles = handles.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = handles.serve_connection(stream) {
+                            log::debug!("Control connection closed: {e}");
+                        }
+                    });
+                }
+                Err(e) => log::warn!("Failed to accept control connection: {e}"),
+            }
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }))
+}
+
+/// Unblocks the server thread so it can notice it should exit
+pub fn wake(path: &Path) {
+    let _ = UnixStream::connect(path);
+}
+
+/// Sends one request to the running app
+pub fn send(path: &Path, request: &Request) -> io::Result<Response> {
+    let mut stream = UnixStream::connect(path)?;
+    serde_json::to_writer(&mut stream, request)?;
+    stream.write_all(b"\n")?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    serde_json::from_str(&line).map_err(io::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn set_theme_only_accepts_known_themes() {
+        let (commands, received) = channel();
+        let handles = Handles {
+            keystroke_state: Arc::default(),
+            theme: Arc::default(),
+            themes: Arc::new(RwLock::new(ThemeSet {
+                ids: vec![Arc::new("standard".to_string())],
+                ..ThemeSet::default()
+            })),
+            commands,
+            context: Context::default(),
+        };
+
+        let unknown = handles.handle(Request::SetTheme {
+            theme: "missing".to_string(),
+        });
+        assert!(matches!(unknown, Response::Error { .. }));
+        assert!(received.try_recv().is_err());
+
+        let known = handles.handle(Request::SetTheme {
+            theme: "standard".to_string(),
+        });
+        assert_eq!(known, Response::Ok);
+        assert_eq!(
+            received.try_recv().unwrap(),
+            AppCommand::SetTheme("standard".to_string())
+        );
+    }
+}
@@ -1,9 +1,29 @@
 #![feature(stmt_expr_attributes)]
 #![feature(thread_sleep_until)]
 #![feature(let_chains)]
-#![feature(unsafe_cell_access)]
 #![warn(clippy::all, rust_2018_idioms)]
 
 pub mod app;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod config;
 pub mod consts;
+#[cfg(all(target_os = "linux", feature = "dbus"))]
+pub mod dbus;
+#[cfg(target_os = "linux")]
+pub mod devices;
+pub mod focus;
+pub mod fullscreen;
+pub mod hotkeys;
+pub mod http;
+#[cfg(unix)]
+pub mod ipc;
+#[cfg(all(target_os = "linux", feature = "dbus"))]
+pub mod lock;
+pub mod metrics;
+pub mod overlay;
+pub mod placement;
+pub mod status;
 pub mod theme;
+#[cfg(feature = "tray")]
+pub mod tray;
@@ -0,0 +1,165 @@
+//! Pauses counting while the session is locked or the screensaver is on, so passwords typed at the lock
+//! screen aren't counted
+use crate::app::keystroke::{KeystrokeState, PauseReason};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, Sender, channel};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use zbus::blocking::Connection;
+use zbus::proxy;
+
+/// How often the watcher wakes up to notice the app closing
+const EXIT_POLL_DELAY: Duration = Duration::from_secs(1);
+
+/// The logind session this process belongs to
+#[proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait Session {
+    /// Set by lock screens while they are up
+    #[zbus(property)]
+    fn locked_hint(&self) -> zbus::Result<bool>;
+
+    /// Asks lock screens to lock the session
+    #[zbus(signal)]
+    fn lock(&self) -> zbus::Result<()>;
+
+    /// Asks lock screens to unlock the session
+    #[zbus(signal)]
+    fn unlock(&self) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.ScreenSaver",
+    default_service = "org.freedesktop.ScreenSaver",
+    default_path = "/org/freedesktop/ScreenSaver"
+)]
+trait ScreenSaver {
+    fn get_active(&self) -> zbus::Result<bool>;
+
+    #[zbus(signal)]
+    fn active_changed(&self, active: bool) -> zbus::Result<()>;
+}
+
+/// A lock state reported by one of the sources
+enum Change {
+    Session(bool),
+    ScreenSaver(bool),
+}
+
+/// Forwards every item of `stream` as a change until the watcher is gone
+fn forward<I: Iterator>(
+    stream: I,
+    changes: Sender<Change>,
+    change: impl Fn(I::Item) -> Option<Change>,
+) {
+    for item in stream {
+        if let Some(change) = change(item)
+            && changes.send(change).is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Listens for logind's lock signals and `LockedHint` changes
+fn follow_session(
+    session: &SessionProxyBlocking<'static>,
+    changes: &Sender<Change>,
+) -> zbus::Result<()> {
+    let hint = session.receive_locked_hint_changed();
+    let lock = session.receive_lock()?;
+    let unlock = session.receive_unlock()?;
+
+    let sender = changes.clone();
+    thread::spawn(move || {
+        forward(hint, sender, |changed| {
+            changed.get().ok().map(Change::Session)
+        });
+    });
+    let sender = changes.clone();
+    thread::spawn(move || forward(lock, sender, |_| Some(Change::Session(true))));
+    let sender = changes.clone();
+    thread::spawn(move || forward(unlock, sender, |_| Some(Change::Session(false))));
+    Ok(())
+}
+
+/// Listens for the screensaver turning on and off
+fn follow_screensaver(
+    screensaver: &ScreenSaverProxyBlocking<'static>,
+    changes: &Sender<Change>,
+) -> zbus::Result<()> {
+    let active = screensaver.receive_active_changed()?;
+
+    let sender = changes.clone();
+    thread::spawn(move || {
+        forward(active, sender, |signal| {
+            signal
+                .args()
+                .ok()
+                .map(|args| Change::ScreenSaver(args.active))
+        });
+    });
+    Ok(())
+}
+
+/// Keeps `PauseReason::Locked` in sync with logind and the screensaver until `exit_notify` is set
+pub fn watch(
+    keystroke_state: Arc<KeystrokeState>,
+    exit_notify: Arc<AtomicBool>,
+) -> Option<JoinHandle<()>> {
+    let (changes, received) = channel();
+
+    let session = Connection::system()
+        .and_then(|connection| SessionProxyBlocking::new(&connection))
+        .and_then(|session| {
+            follow_session(&session, &changes)?;
+            session.locked_hint()
+        })
+        .inspect_err(|e| log::debug!("Can't reach logind: {e}"))
+        .ok();
+    let screensaver = Connection::session()
+        .and_then(|connection| ScreenSaverProxyBlocking::new(&connection))
+        .and_then(|screensaver| {
+            follow_screensaver(&screensaver, &changes)?;
+            screensaver.get_active()
+        })
+        .inspect_err(|e| log::debug!("Can't reach the screensaver: {e}"))
+        .ok();
+    if session.is_none() && screensaver.is_none() {
+        log::warn!("Can't tell when the session is locked, so locking won't pause counting");
+        return None;
+    }
+    drop(changes);
+
+    let mut session_locked = session.unwrap_or(false);
+    let mut screensaver_active = screensaver.unwrap_or(false);
+
+    Some(thread::spawn(move || {
+        loop {
+            let locked = session_locked || screensaver_active;
+            if locked != keystroke_state.is_paused_for(PauseReason::Locked) {
+                log::info!(
+                    "{} counting, the session was {}",
+                    if locked { "Pausing" } else { "Resuming" },
+                    if locked { "locked" } else { "unlocked" }
+                );
+                keystroke_state.set_paused_for(PauseReason::Locked, locked);
+            }
+
+            match received.recv_timeout(EXIT_POLL_DELAY) {
+                Ok(Change::Session(locked)) => session_locked = locked,
+                Ok(Change::ScreenSaver(active)) => screensaver_active = active,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+
+            if exit_notify.load(Ordering::Relaxed) {
+                return;
+            }
+        }
+    }))
+}
@@ -2,40 +2,169 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide the console window on Windows in release
 #![feature(stmt_expr_attributes)]
 
-use bongocat_rs::app::BongoApp;
-use bongocat_rs::consts::graphics::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use bongocat_rs::app::keystroke::Paw;
+use bongocat_rs::app::{BongoApp, layout};
+use bongocat_rs::config::{APP_ID, Config};
+use bongocat_rs::consts::graphics::{MAX_SCALE, MIN_SCALE};
+use bongocat_rs::placement;
+use bongocat_rs::status::{self, StatusFormat};
+use clap::{Parser, Subcommand};
 use display_info::DisplayInfo;
 use egui::WindowLevel;
 use env_logger::Env;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Control the running instance
+    Ctl {
+        #[command(subcommand)]
+        request: CtlRequest,
+    },
+    /// Print a status-bar line with keystrokes per minute and the session count whenever they change
+    Status {
+        #[arg(long, value_enum, default_value_t)]
+        format: StatusFormat,
+        /// Seconds between updates
+        #[arg(long, default_value_t = 1.0)]
+        interval: f64,
+        /// Count keystrokes in this process instead of asking the running instance
+        #[arg(long)]
+        standalone: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CtlRequest {
+    /// Print keystroke counts and other stats as JSON
+    Stats,
+    /// Switch to another theme
+    Theme { id: String },
+    /// Stop counting keystrokes
+    Pause,
+    /// Start counting keystrokes again
+    Resume,
+    /// Set the session keystroke count back to zero
+    Reset,
+    /// Make the cat slap
+    Slap {
+        #[arg(value_enum, default_value_t)]
+        paw: Paw,
+    },
+    /// Close the app
+    Quit,
+}
+
+#[cfg(unix)]
+fn ctl(config: &Config, request: CtlRequest) -> i32 {
+    use bongocat_rs::ipc::{self, Request, Response};
+
+    let request = match request {
+        CtlRequest::Stats => Request::Stats,
+        CtlRequest::Theme { id } => Request::SetTheme { theme: id },
+        CtlRequest::Pause => Request::Pause,
+        CtlRequest::Resume => Request::Resume,
+        CtlRequest::Reset => Request::Reset,
+        CtlRequest::Slap { paw } => Request::Slap { paw },
+        CtlRequest::Quit => Request::Quit,
+    };
+
+    let path = ipc::socket_path(&config.ipc);
+    match ipc::send(&path, &request) {
+        Ok(Response::Ok) => 0,
+        Ok(Response::Stats(stats)) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&stats).expect("Stats are always valid JSON")
+            );
+            0
+        }
+        Ok(Response::Error { message }) => {
+            eprintln!("{message}");
+            1
+        }
+        Err(e) => {
+            eprintln!("Could not reach Bongocat at {}: {e}", path.display());
+            1
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn ctl(_config: &Config, _request: CtlRequest) -> i32 {
+    eprintln!("`ctl` is only supported on Unix");
+    1
+}
+
+fn status(config: &Config, format: StatusFormat, interval: f64, standalone: bool) -> i32 {
+    let interval = Duration::from_secs_f64(interval.max(0.1));
+
+    #[cfg(unix)]
+    if !standalone {
+        use bongocat_rs::ipc::{self, Request, Response};
+
+        let path = ipc::socket_path(&config.ipc);
+        let stats = || match ipc::send(&path, &Request::Stats) {
+            Ok(Response::Stats(stats)) => Some(status::Sample::from(stats)),
+            _ => None,
+        };
+        if stats().is_some() {
+            return i32::from(status::run(format, interval, stats).is_err());
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (config, standalone);
+
+    let state = status::capture();
+    i32::from(status::run(format, interval, || Some(status::Sample::new(&state))).is_err())
+}
 
 fn main() -> eframe::Result {
-    env_logger::init();
+    env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+
+    let cli = Cli::parse();
+    let config = Config::load();
+
+    match cli.command {
+        Some(Command::Ctl { request }) => std::process::exit(ctl(&config, request)),
+        Some(Command::Status {
+            format,
+            interval,
+            standalone,
+        }) => std::process::exit(status(&config, format, interval, standalone)),
+        None => {}
+    }
 
     println!("{:?}", Env::default());
 
-    let displays = DisplayInfo::all().unwrap();
-    let primary = displays
-        .iter()
-        .find(|d| d.is_primary)
-        .unwrap_or_else(|| {
-            displays
-                .first()
-                .unwrap_or_else(|| panic!("Could not find primary display"))
-        })
-        .clone();
+    let size = layout::window_size(config.window.scale.clamp(MIN_SCALE, MAX_SCALE));
+
+    let displays = DisplayInfo::all().unwrap_or_else(|e| {
+        log::error!("Could not list displays: {e}");
+        vec![]
+    });
+    let position = placement::window_position(&config.window, &displays, size, None);
 
     let native_options = eframe::NativeOptions {
         window_builder: Some(Box::new(move |builder| {
-            builder.with_position([
-                (primary.x + (primary.width as i32)) as f32 - WINDOW_HEIGHT,
-                (primary.y + (primary.height as i32)) as f32 - WINDOW_WIDTH,
-            ])
+            if let Some(position) = position {
+                builder.with_position(position)
+            } else {
+                builder
+            }
         })),
         viewport: egui::ViewportBuilder::default()
             .with_transparent(true)
-            .with_inner_size([WINDOW_WIDTH, WINDOW_HEIGHT])
-            .with_min_inner_size([WINDOW_WIDTH, WINDOW_HEIGHT])
-            .with_max_inner_size([WINDOW_WIDTH, WINDOW_HEIGHT])
+            .with_inner_size(size)
+            .with_min_inner_size(size)
+            .with_max_inner_size(size)
             .with_always_on_top()
             .with_decorations(false)
             .with_window_level(WindowLevel::AlwaysOnTop)
@@ -49,8 +178,8 @@ fn main() -> eframe::Result {
     };
 
     eframe::run_native(
-        "Bongocat",
+        APP_ID,
         native_options,
-        Box::new(|cc| Ok(Box::new(BongoApp::new(cc)))),
+        Box::new(|cc| Ok(Box::new(BongoApp::new(cc, config)))),
     )
 }
@@ -0,0 +1,275 @@
+//! Local `/metrics` endpoint exporting typing metrics for Prometheus and other OpenMetrics scrapers
+use crate::app::keystroke::KeystrokeState;
+use crate::config::{MetricsConfig, PrivacyMode};
+use crate::http::{self, Request};
+use std::fmt::Write;
+use std::io;
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Writes metric families in either the Prometheus or the OpenMetrics text format
+struct Exposition {
+    openmetrics: bool,
+    text: String,
+}
+
+impl Exposition {
+    /// Starts a family. Counters are named without `_total`, which is added to their samples.
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let name = if kind == "counter" && !self.openmetrics {
+            format!("{name}_total")
+        } else {
+            name.to_string()
+        };
+        let _ = writeln!(self.text, "# HELP {name} {help}");
+        let _ = writeln!(self.text, "# TYPE {name} {kind}");
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl std::fmt::Display) {
+        self.text.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(key, value)| {
+                    let value = value
+                        .replace('\\', r"\\")
+                        .replace('"', "\\\"")
+                        .replace('\n', r"\n");
+                    format!("{key}=\"{value}\"")
+                })
+                .collect();
+            let _ = write!(self.text, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.text, " {value}");
+    }
+
+    fn finish(mut self) -> String {
+        if self.openmetrics {
+            self.text.push_str("# EOF\n");
+        }
+        self.text
+    }
+}
+
+/// Renders every metric
+fn render(state: &KeystrokeState, openmetrics: bool) -> String {
+    let mut out = Exposition {
+        openmetrics,
+        text: String::new(),
+    };
+
+    out.family(
+        "bongocat_keystrokes",
+        "counter",
+        "Keystrokes counted since the stats were first saved.",
+    );
+    out.sample(
+        "bongocat_keystrokes_total",
+        &[],
+        state.keystrokes.load(Ordering::Relaxed),
+    );
+
+    // Only export what privacy mode lets us keep
+    if state.privacy == PrivacyMode::Off {
+        out.family(
+            "bongocat_key_presses",
+            "counter",
+            "Keystrokes counted per key.",
+        );
+        for (key, presses) in state.top_keys(usize::MAX) {
+            out.sample(
+                "bongocat_key_presses_total",
+                &[("key", &format!("{key:?}"))],
+                presses,
+            );
+        }
+    }
+    if state.privacy != PrivacyMode::Aggregate {
+        out.family(
+            "bongocat_key_class_presses",
+            "counter",
+            "Keystrokes counted per kind of key.",
+        );
+        for (class, presses) in state.key_classes() {
+            out.sample(
+                "bongocat_key_class_presses_total",
+                &[("class", &format!("{class:?}").to_lowercase())],
+                presses,
+            );
+        }
+    }
+
+    out.family(
+        "bongocat_session_keystrokes",
+        "gauge",
+        "Keystrokes counted since launch or the last session reset.",
+    );
+    out.sample(
+        "bongocat_session_keystrokes",
+        &[],
+        state.session_keystrokes.load(Ordering::Relaxed),
+    );
+
+    out.family(
+        "bongocat_keystrokes_per_minute",
+        "gauge",
+        "Keystrokes counted over the last minute.",
+    );
+    out.sample(
+        "bongocat_keystrokes_per_minute",
+        &[],
+        state.keystrokes_per_minute(),
+    );
+
+    out.family(
+        "bongocat_uptime_seconds",
+        "gauge",
+        "Seconds since the app started.",
+    );
+    out.sample("bongocat_uptime_seconds", &[], state.uptime().as_secs_f64());
+
+    out.family(
+        "bongocat_paused",
+        "gauge",
+        "Whether counting is paused, 1 if so.",
+    );
+    out.sample("bongocat_paused", &[], u8::from(state.is_paused()));
+
+    out.finish()
+}
+
+fn handle(state: &KeystrokeState, stream: TcpStream, request: Request) -> io::Result<()> {
+    if request.method != "GET" || request.path.split('?').next() != Some("/metrics") {
+        return http::not_found(&stream);
+    }
+
+    let openmetrics = request
+        .header("Accept")
+        .is_some_and(|accept| accept.contains("application/openmetrics-text"));
+    let content_type = if openmetrics {
+        OPENMETRICS_CONTENT_TYPE
+    } else {
+        PROMETHEUS_CONTENT_TYPE
+    };
+
+    http::respond(
+        &stream,
+        "200 OK",
+        content_type,
+        render(state, openmetrics).as_bytes(),
+    )
+}
+
+/// Starts the metrics server on the configured address
+pub fn serve(
+    config: &MetricsConfig,
+    keystroke_state: Arc<KeystrokeState>,
+    exit_notify: Arc<AtomicBool>,
+) -> io::Result<(SocketAddr, JoinHandle<()>)> {
+    http::serve(
+        &config.address,
+        move |stream, request| handle(&keystroke_state, stream, request),
+        exit_notify,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use inputbot::KeybdKey;
+    use std::io::{Read, Write as _};
+
+    fn typed(privacy: PrivacyMode) -> KeystrokeState {
+        let mut state = KeystrokeState::default();
+        state.set_privacy(privacy);
+        state.log_keystroke(&KeybdKey::AKey);
+        state.log_keystroke(&KeybdKey::AKey);
+        state.log_keystroke(&KeybdKey::Numrow1Key);
+        state
+    }
+
+    #[test]
+    fn prometheus_counters_end_in_total() {
+        let text = render(&typed(PrivacyMode::Off), false);
+        assert!(text.contains("# TYPE bongocat_keystrokes_total counter\n"));
+        assert!(text.contains("\nbongocat_keystrokes_total 3\n"));
+        assert!(text.contains("\nbongocat_key_presses_total{key=\"AKey\"} 2\n"));
+        assert!(text.contains("\nbongocat_key_class_presses_total{class=\"digit\"} 1\n"));
+        assert!(text.contains("\nbongocat_session_keystrokes 3\n"));
+        assert!(text.contains("\nbongocat_paused 0\n"));
+        assert!(!text.contains("# EOF"));
+    }
+
+    #[test]
+    fn openmetrics_names_families_without_total_and_ends_with_eof() {
+        let text = render(&typed(PrivacyMode::Off), true);
+        assert!(text.contains("# TYPE bongocat_keystrokes counter\n"));
+        assert!(text.contains("\nbongocat_keystrokes_total 3\n"));
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn privacy_modes_leave_out_what_they_forget() {
+        let classes = render(&typed(PrivacyMode::Classes), false);
+        assert!(!classes.contains("bongocat_key_presses"));
+        assert!(classes.contains("bongocat_key_class_presses_total{class=\"letter\"} 2"));
+
+        let aggregate = render(&typed(PrivacyMode::Aggregate), false);
+        assert!(!aggregate.contains("bongocat_key_presses"));
+        assert!(!aggregate.contains("bongocat_key_class_presses"));
+        assert!(aggregate.contains("\nbongocat_keystrokes_total 3\n"));
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        let mut out = Exposition {
+            openmetrics: false,
+            text: String::new(),
+        };
+        out.sample("metric", &[("label", "a\"b\\c\nd")], 1);
+        assert_eq!(out.finish(), "metric{label=\"a\\\"b\\\\c\\nd\"} 1\n");
+    }
+
+    /// Sends a bare `GET /metrics` with `host` as its `Host`, returning the response
+    fn get(address: SocketAddr, host: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET /metrics HTTP/1.1\r\nHost: {host}\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn refuses_requests_for_other_hosts() {
+        let config = MetricsConfig {
+            enabled: true,
+            address: "127.0.0.1:0".to_string(),
+        };
+        let exit_notify = Arc::new(AtomicBool::new(false));
+        let (address, handle) = serve(
+            &config,
+            Arc::new(typed(PrivacyMode::Off)),
+            exit_notify.clone(),
+        )
+        .unwrap();
+        let port = address.port();
+
+        let own = get(address, &format!("localhost:{port}"));
+        assert!(own.starts_with("HTTP/1.1 200 OK"), "{own}");
+        assert!(own.contains("bongocat_key_presses_total{key=\"AKey\"} 2"));
+
+        // A page on another site that rebound its name to 127.0.0.1
+        let rebound = get(address, &format!("evil.example:{port}"));
+        assert!(rebound.starts_with("HTTP/1.1 403"), "{rebound}");
+        assert!(!rebound.contains("bongocat_"));
+
+        exit_notify.store(true, Ordering::Relaxed);
+        http::wake(address);
+        handle.join().unwrap();
+    }
+}
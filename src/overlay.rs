@@ -0,0 +1,232 @@
+//! Local HTTP + WebSocket server streaming the cat's state as JSON, along with a page that draws the
+//! cat from it, so it can be added to OBS as a browser source
+use crate::app::keystroke::{KeystrokeEvent, KeystrokeState, PawState};
+use crate::config::OverlayConfig;
+use crate::http::{self, Request};
+use crate::theme::{AppTheme, EMBEDDED_THEMES, FRAME_FILES};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Error, Message, WebSocket};
+
+const PAGE: &str = include_str!("../assets/overlay.html");
+
+/// Where a frame's PNG comes from
+#[derive(Clone)]
+enum FrameSource {
+    Embedded(&'static [u8]),
+    File(PathBuf),
+}
+
+impl FrameSource {
+    fn read(&self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Embedded(png) => Ok(png.to_vec()),
+            Self::File(path) => std::fs::read(path),
+        }
+    }
+}
+
+/// Sent when a client connects and whenever the theme changes
+#[derive(serde::Serialize)]
+struct Snapshot<'a> {
+    r#type: &'static str,
+    keystrokes: usize,
+    session_keystrokes: usize,
+    paws: PawState,
+    theme: &'a str,
+    themes: &'a [String],
+}
+
+struct Overlay {
+    frames: HashMap<String, [FrameSource; 4]>,
+    /// Whether `key-pressed` events say which key it was
+    send_keys: bool,
+    theme_ids: Vec<String>,
+    keystroke_state: Arc<KeystrokeState>,
+    theme: Arc<RwLock<String>>,
+    exit_notify: Arc<AtomicBool>,
+}
+
+impl Overlay {
+    fn handle(&self, stream: TcpStream, request: Request) -> io::Result<()> {
+        if request.method != "GET" {
+            return http::respond(&stream, "405 Method Not Allowed", "text/plain", b"");
+        }
+
+        let path = request.path.split('?').next().unwrap_or_default();
+        match path.trim_start_matches('/').split('/').collect::<Vec<_>>()[..] {
+            [""] | ["index.html"] => http::respond(
+                &stream,
+                "200 OK",
+                "text/html; charset=utf-8",
+                PAGE.as_bytes(),
+            ),
+            ["ws"] => self.websocket(stream, &request),
+            ["frames", theme, file] => {
+                let frame = self
+                    .frames
+                    .get(theme)
+                    .zip(FRAME_FILES.iter().position(|f| *f == file))
+                    .map(|(frames, index)| frames[index].read());
+                match frame {
+                    Some(Ok(png)) => http::respond(&stream, "200 OK", "image/png", &png),
+                    _ => http::not_found(&stream),
+                }
+            }
+            _ => http::not_found(&stream),
+        }
+    }
+
+    /// `event` as sent to clients, leaving out which key was pressed unless `send_keys` is on
+    fn redact(&self, event: KeystrokeEvent) -> KeystrokeEvent {
+        match event {
+            KeystrokeEvent::KeyPressed {
+                keystrokes,
+                session_keystrokes,
+                ..
+            } if !self.send_keys => KeystrokeEvent::KeyPressed {
+                key: None,
+                keystrokes,
+                session_keystrokes,
+            },
+            event => event,
+        }
+    }
+
+    /// Handles whatever the client sent without waiting for more. Returns `false` once it's gone.
+    fn read_incoming(socket: &mut WebSocket<TcpStream>) -> io::Result<bool> {
+        socket.get_ref().set_nonblocking(true)?;
+        let result = loop {
+            // Pings are answered and closes acknowledged by tungstenite while reading
+            match socket.read() {
+                Ok(Message::Close(_)) => break Ok(false),
+                Ok(_) => {}
+                Err(Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => break Ok(true),
+                Err(Error::ConnectionClosed | Error::AlreadyClosed) => break Ok(false),
+                Err(e) => break Err(io::Error::other(e)),
+            }
+        };
+        socket.get_ref().set_nonblocking(false)?;
+        match socket.flush() {
+            Ok(()) | Err(Error::ConnectionClosed) => result,
+            Err(e) => Err(io::Error::other(e)),
+        }
+    }
+
+    fn send<T: serde::Serialize>(socket: &mut WebSocket<TcpStream>, message: &T) -> io::Result<()> {
+        let json = serde_json::to_string(message)?;
+        socket.send(Message::text(json)).map_err(io::Error::other)
+    }
+
+    fn send_snapshot(&self, socket: &mut WebSocket<TcpStream>, theme: &str) -> io::Result<()> {
+        Self::send(
+            socket,
+            &Snapshot {
+                r#type: "state",
+                keystrokes: self.keystroke_state.keystrokes.load(Ordering::Relaxed),
+                session_keystrokes: self
+                    .keystroke_state
+                    .session_keystrokes
+                    .load(Ordering::Relaxed),
+                paws: self.keystroke_state.published_paws(),
+                theme,
+                themes: &self.theme_ids,
+            },
+        )
+    }
+
+    fn websocket(&self, stream: TcpStream, request: &Request) -> io::Result<()> {
+        let Some(key) = request.header("Sec-WebSocket-Key") else {
+            return http::respond(&stream, "400 Bad Request", "text/plain", b"");
+        };
+        write!(
+            &stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            derive_accept_key(key.as_bytes())
+        )?;
+
+        let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+        let events = self.keystroke_state.subscribe();
+        let mut theme = self.theme.read().clone();
+        self.send_snapshot(&mut socket, &theme)?;
+
+        // Wake up every so often to pick up theme changes, answer pings and notice when the app is
+        // closing
+        loop {
+            match events.recv_timeout(Duration::from_secs(1)) {
+                Ok(event) => Self::send(&mut socket, &self.redact(event))?,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+
+            if !Self::read_incoming(&mut socket)? {
+                return Ok(());
+            }
+
+            if self.exit_notify.load(Ordering::Relaxed) {
+                let _ = socket.close(None);
+                return Ok(());
+            }
+
+            let latest = self.theme.read().clone();
+            if latest != theme {
+                theme = latest;
+                self.send_snapshot(&mut socket, &theme)?;
+            }
+        }
+    }
+}
+
+/// Starts the overlay server on the configured address
+pub fn serve(
+    config: &OverlayConfig,
+    themes: &[AppTheme],
+    keystroke_state: Arc<KeystrokeState>,
+    theme: Arc<RwLock<String>>,
+    exit_notify: Arc<AtomicBool>,
+) -> io::Result<(SocketAddr, JoinHandle<()>)> {
+    let mut frames: HashMap<String, [FrameSource; 4]> = EMBEDDED_THEMES
+        .iter()
+        .map(|(id, pngs)| (id.to_string(), pngs.map(FrameSource::Embedded)))
+        .collect();
+    for theme in themes {
+        frames.insert(
+            theme.id.to_string(),
+            [
+                &theme.paws_both,
+                &theme.paws_left,
+                &theme.paws_right,
+                &theme.paws_up,
+            ]
+            .map(|path| FrameSource::File(path.clone())),
+        );
+    }
+
+    let mut theme_ids: Vec<String> = frames.keys().cloned().collect();
+    theme_ids.sort();
+
+    let overlay = Overlay {
+        frames,
+        send_keys: config.send_keys,
+        theme_ids,
+        keystroke_state,
+        theme,
+        exit_notify: exit_notify.clone(),
+    };
+
+    http::serve(
+        &config.address,
+        move |stream, request| overlay.handle(stream, request),
+        exit_notify,
+    )
+}
@@ -0,0 +1,143 @@
+//! Picking a display and a spot on it for the window
+use crate::config::WindowConfig;
+use display_info::DisplayInfo;
+use egui::{Pos2, Rect, Vec2};
+
+/// Which display to put the window on
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+pub enum MonitorSelector {
+    /// Position in the list of displays, starting at 0
+    Index(usize),
+    /// Display name as reported by the OS, or `"primary"`
+    Name(String),
+}
+
+impl Default for MonitorSelector {
+    fn default() -> Self {
+        Self::Name("primary".to_string())
+    }
+}
+
+/// Corner of the display the window is anchored to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+fn primary(displays: &[DisplayInfo]) -> Option<&DisplayInfo> {
+    displays
+        .iter()
+        .find(|d| d.is_primary)
+        .or_else(|| displays.first())
+}
+
+/// Finds the selected display, falling back to the primary one when it's gone
+#[must_use]
+pub fn choose_display<'a>(
+    displays: &'a [DisplayInfo],
+    monitor: &MonitorSelector,
+) -> Option<&'a DisplayInfo> {
+    let chosen = match monitor {
+        MonitorSelector::Index(index) => displays.get(*index),
+        MonitorSelector::Name(name) if name.eq_ignore_ascii_case("primary") => primary(displays),
+        MonitorSelector::Name(name) => displays.iter().find(|d| &d.name == name),
+    };
+
+    chosen.or_else(|| {
+        log::warn!("Display {monitor:?} not found, falling back to the primary display");
+        primary(displays)
+    })
+}
+
+/// Bounds of a display in desktop coordinates
+#[must_use]
+pub fn display_rect(display: &DisplayInfo) -> Rect {
+    Rect::from_min_size(
+        Pos2::new(display.x as f32, display.y as f32),
+        Vec2::new(display.width as f32, display.height as f32),
+    )
+}
+
+/// Top-left position for a window of `size`, `offset` away from `corner` of a display's `bounds`
+#[must_use]
+pub fn corner_position(bounds: Rect, corner: Corner, offset: Vec2, size: Vec2) -> Pos2 {
+    let x = match corner {
+        Corner::TopLeft | Corner::BottomLeft => bounds.left() + offset.x,
+        Corner::TopRight | Corner::BottomRight => bounds.right() - size.x - offset.x,
+    };
+    let y = match corner {
+        Corner::TopLeft | Corner::TopRight => bounds.top() + offset.y,
+        Corner::BottomLeft | Corner::BottomRight => bounds.bottom() - size.y - offset.y,
+    };
+
+    Pos2::new(x, y)
+}
+
+/// Whether a window at `position` would still be on one of `displays`
+#[must_use]
+pub fn is_on_screen(displays: &[DisplayInfo], position: Pos2, size: Vec2) -> bool {
+    let window = Rect::from_min_size(position, size);
+    displays
+        .iter()
+        .any(|d| display_rect(d).contains(window.center()))
+}
+
+/// Where the window should open: the remembered position if it's still on screen, otherwise the
+/// configured corner of the configured display
+#[must_use]
+pub fn window_position(
+    config: &WindowConfig,
+    displays: &[DisplayInfo],
+    size: Vec2,
+    remembered: Option<Pos2>,
+) -> Option<Pos2> {
+    if config.remember_position
+        && let Some(position) = remembered
+        && is_on_screen(displays, position, size)
+    {
+        return Some(position);
+    }
+
+    choose_display(displays, &config.monitor).map(|display| {
+        corner_position(
+            display_rect(display),
+            config.corner,
+            Vec2::from(config.offset),
+            size,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1920x1080 display right of another one
+    const BOUNDS: Rect = Rect::from_min_max(Pos2::new(1920.0, 0.0), Pos2::new(3840.0, 1080.0));
+    const SIZE: Vec2 = Vec2::new(250.0, 250.0);
+
+    #[test]
+    fn corners_without_offset() {
+        let at = |corner| corner_position(BOUNDS, corner, Vec2::ZERO, SIZE);
+        assert_eq!(at(Corner::TopLeft), Pos2::new(1920.0, 0.0));
+        assert_eq!(at(Corner::TopRight), Pos2::new(3590.0, 0.0));
+        assert_eq!(at(Corner::BottomLeft), Pos2::new(1920.0, 830.0));
+        assert_eq!(at(Corner::BottomRight), Pos2::new(3590.0, 830.0));
+    }
+
+    #[test]
+    fn offset_moves_away_from_the_corner() {
+        let offset = Vec2::new(10.0, 40.0);
+        let at = |corner| corner_position(BOUNDS, corner, offset, SIZE);
+        assert_eq!(at(Corner::TopLeft), Pos2::new(1930.0, 40.0));
+        assert_eq!(at(Corner::TopRight), Pos2::new(3580.0, 40.0));
+        assert_eq!(at(Corner::BottomLeft), Pos2::new(1930.0, 790.0));
+        assert_eq!(at(Corner::BottomRight), Pos2::new(3580.0, 790.0));
+    }
+}
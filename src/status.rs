@@ -0,0 +1,178 @@
+//! Headless status-bar output: one line per update with keystrokes per minute, the session count and a
+//! tiny cat, for waybar, i3blocks, polybar and friends
+use crate::app::keystroke::KeystrokeState;
+use crate::consts::keyboard::KEYS;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+/// How each line is printed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatusFormat {
+    /// JSON for waybar's `custom` modules with `return-type: json`
+    #[default]
+    Waybar,
+    /// JSON for i3blocks' `format=json` with `interval=persist`
+    I3blocks,
+    /// Plain text, e.g. for polybar's `tail = true` scripts
+    Text,
+}
+
+/// What the cat is up to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mood {
+    Paused,
+    Idle,
+    Typing,
+    Frantic,
+}
+
+impl Mood {
+    /// Keystrokes per minute from which the cat slaps with both paws
+    const FRANTIC_KPM: usize = 300;
+
+    fn new(sample: &Sample) -> Self {
+        match sample.keystrokes_per_minute {
+            _ if sample.paused => Self::Paused,
+            0 => Self::Idle,
+            kpm if kpm >= Self::FRANTIC_KPM => Self::Frantic,
+            _ => Self::Typing,
+        }
+    }
+
+    fn glyph(self) -> &'static str {
+        match self {
+            Self::Paused => "(=-ω-=)",
+            Self::Idle => "(=^･ω･^=)",
+            Self::Typing => "(=^･ω･^)ฅ",
+            Self::Frantic => "ฅ(=^･ω･^=)ฅ",
+        }
+    }
+
+    /// CSS class for waybar
+    fn class(self) -> &'static str {
+        match self {
+            Self::Paused => "paused",
+            Self::Idle => "idle",
+            Self::Typing => "typing",
+            Self::Frantic => "frantic",
+        }
+    }
+}
+
+/// The counters shown on one line
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub keystrokes: usize,
+    pub session_keystrokes: usize,
+    pub keystrokes_per_minute: usize,
+    pub paused: bool,
+}
+
+impl Sample {
+    #[must_use]
+    pub fn new(state: &KeystrokeState) -> Self {
+        Self {
+            keystrokes: state.keystrokes.load(Ordering::Relaxed),
+            session_keystrokes: state.session_keystrokes.load(Ordering::Relaxed),
+            keystrokes_per_minute: state.keystrokes_per_minute(),
+            paused: state.is_paused(),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<crate::ipc::Stats> for Sample {
+    fn from(stats: crate::ipc::Stats) -> Self {
+        Self {
+            keystrokes: stats.keystrokes,
+            session_keystrokes: stats.session_keystrokes,
+            keystrokes_per_minute: stats.keystrokes_per_minute,
+            paused: stats.paused,
+        }
+    }
+}
+
+/// Formats one line, without the trailing newline. `None` means the running instance went away.
+#[must_use]
+pub fn format_line(format: StatusFormat, sample: Option<&Sample>) -> String {
+    let Some(sample) = sample else {
+        return match format {
+            StatusFormat::Waybar => serde_json::json!({
+                "text": "(=x.x=)",
+                "tooltip": "Bongocat is not running",
+                "class": "offline",
+            })
+            .to_string(),
+            StatusFormat::I3blocks => serde_json::json!({
+                "full_text": "(=x.x=)",
+                "short_text": "(=x.x=)",
+            })
+            .to_string(),
+            StatusFormat::Text => "(=x.x=)".to_string(),
+        };
+    };
+
+    let mood = Mood::new(sample);
+    let text = format!(
+        "{} {} kpm · {}",
+        mood.glyph(),
+        sample.keystrokes_per_minute,
+        sample.session_keystrokes
+    );
+    match format {
+        StatusFormat::Waybar => serde_json::json!({
+            "text": text,
+            "alt": mood.class(),
+            "class": mood.class(),
+            "tooltip": format!(
+                "{} keystrokes per minute\n{} this session\n{} in total",
+                sample.keystrokes_per_minute, sample.session_keystrokes, sample.keystrokes
+            ),
+        })
+        .to_string(),
+        StatusFormat::I3blocks => serde_json::json!({
+            "full_text": text,
+            "short_text": format!("{} {}", mood.glyph(), sample.keystrokes_per_minute),
+        })
+        .to_string(),
+        StatusFormat::Text => text,
+    }
+}
+
+/// Prints a line every `interval`, only when it changed, until stdout closes
+pub fn run(
+    format: StatusFormat,
+    interval: Duration,
+    mut sample: impl FnMut() -> Option<Sample>,
+) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    let mut last = None;
+    loop {
+        let line = format_line(format, sample().as_ref());
+        if last.as_ref() != Some(&line) {
+            writeln!(stdout, "{line}")?;
+            stdout.flush()?;
+            last = Some(line);
+        }
+        thread::sleep(interval);
+    }
+}
+
+/// Counts keystrokes itself, for when no instance is running
+#[must_use]
+pub fn capture() -> Arc<KeystrokeState> {
+    let state = Arc::new(KeystrokeState::default());
+    for row in &KEYS {
+        for key in *row {
+            let state = state.clone();
+            let key = key.key;
+            key.bind(move || state.log_keystroke(&key));
+        }
+    }
+
+    thread::spawn(|| inputbot::handle_input_events(true));
+    state
+}
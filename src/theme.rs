@@ -1,86 +1,582 @@
-use crate::app::helpers::color_image_from_dynamic;
-use egui::{ColorImage, TextureHandle};
+use crate::app::command::AppCommand;
+use crate::app::helpers::{
+    color_image_from_dynamic, load_color_image_from_path, load_texture_from_color_image,
+};
+use crate::app::keystroke::KeyClass;
+use crate::consts::graphics::{CAT_ANGLE, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::consts::keyboard::VisualKeybdKeyHolder;
+use egui::{Align, Color32, ColorImage, Context, TextureHandle};
 use image::ImageFormat;
+use parking_lot::{Mutex, RwLock};
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
 
+/// Suffix marking a theme as another theme's "o" face, shown while an O or 0 key is held
+pub const O_FACE_SUFFIX: &str = "-o";
+
+/// Frames every theme directory has to contain
+pub const FRAME_FILES: [&str; 4] = [
+    "paws_both.png",
+    "paws_left.png",
+    "paws_right.png",
+    "paws_up.png",
+];
+
+/// Optional image drawn behind the cat in the `image` background mode
+pub const BACKGROUND_FILE: &str = "background.png";
+
+/// Optional frames shown while nobody is typing, looked up in a theme's `idle` directory
+pub const IDLE_FRAME_FILES: [&str; 4] = ["blink.png", "yawn.png", "sleep.png", "wake.png"];
+
+/// One of the optional idle frames
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdleFrame {
+    Blink,
+    Yawn,
+    Sleep,
+    /// Shown briefly on the first keystroke after sleeping
+    Wake,
+}
+
+/// Whichever idle frames a theme has
+#[derive(Clone)]
+pub struct IdleFrames<T> {
+    pub blink: Option<T>,
+    pub yawn: Option<T>,
+    pub sleep: Option<T>,
+    pub wake: Option<T>,
+}
+
+impl<T> Default for IdleFrames<T> {
+    fn default() -> Self {
+        Self {
+            blink: None,
+            yawn: None,
+            sleep: None,
+            wake: None,
+        }
+    }
+}
+
+impl<T> IdleFrames<T> {
+    #[must_use]
+    pub fn get(&self, frame: IdleFrame) -> Option<&T> {
+        match frame {
+            IdleFrame::Blink => self.blink.as_ref(),
+            IdleFrame::Yawn => self.yawn.as_ref(),
+            IdleFrame::Sleep => self.sleep.as_ref(),
+            IdleFrame::Wake => self.wake.as_ref(),
+        }
+    }
+
+    /// Converts each frame present, along with its name from `IDLE_FRAME_FILES` minus the extension
+    pub fn map<U>(&self, mut f: impl FnMut(&'static str, &T) -> U) -> IdleFrames<U> {
+        IdleFrames {
+            blink: self.blink.as_ref().map(|frame| f("blink", frame)),
+            yawn: self.yawn.as_ref().map(|frame| f("yawn", frame)),
+            sleep: self.sleep.as_ref().map(|frame| f("sleep", frame)),
+            wake: self.wake.as_ref().map(|frame| f("wake", frame)),
+        }
+    }
+}
+
+/// Name of the optional manifest in a theme directory
+pub const MANIFEST_FILE: &str = "theme.toml";
+
+/// A color written as `#rrggbb` or `#rrggbbaa`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HexColor(pub Color32);
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+
+    fn try_from(hex: String) -> Result<Self, Self::Error> {
+        let digits = hex.strip_prefix('#').unwrap_or(&hex);
+        let channel = |i: usize| {
+            digits
+                .get(i..i + 2)
+                .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+        };
+        let color = match digits.len() {
+            6 => channel(0)
+                .zip(channel(2))
+                .zip(channel(4))
+                .map(|((r, g), b)| Color32::from_rgb(r, g, b)),
+            8 => channel(0)
+                .zip(channel(2))
+                .zip(channel(4))
+                .zip(channel(6))
+                .map(|(((r, g), b), a)| Color32::from_rgba_unmultiplied(r, g, b, a)),
+            _ => None,
+        };
+        color
+            .map(Self)
+            .ok_or_else(|| format!("\"{hex}\" is not a #rrggbb or #rrggbbaa color"))
+    }
+}
+
+impl From<HexColor> for String {
+    fn from(color: HexColor) -> Self {
+        let [r, g, b, a] = color.0.to_srgba_unmultiplied();
+        format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+    }
+}
+
+/// Colors a theme can change
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ThemeColors {
+    /// Fill of a pressed key
+    pub key: HexColor,
+    /// Outline of every key
+    pub outline: HexColor,
+    /// Ripples and particles bursting from pressed keys
+    pub ripple: HexColor,
+    /// Keystroke counter text
+    pub counter: HexColor,
+}
+
+impl Default for ThemeColors {
+    fn default() -> Self {
+        Self {
+            key: HexColor(Color32::LIGHT_BLUE),
+            outline: HexColor(Color32::WHITE),
+            ripple: HexColor(Color32::LIGHT_BLUE),
+            counter: HexColor(Color32::WHITE),
+        }
+    }
+}
+
+/// Colors overriding `ThemeColors` for some keys
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct KeyColors {
+    /// Fill while pressed
+    pub key: Option<HexColor>,
+    pub outline: Option<HexColor>,
+}
+
+/// How the keyboard overlay is drawn
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct OverlayStyle {
+    /// Whether to draw the keyboard overlay at all
+    pub visible: bool,
+    /// Width of key outlines, before scaling
+    pub stroke_width: f32,
+    /// Radius of key corners, before scaling
+    pub rounding: f32,
+    /// Colors of each row, from the function keys down
+    pub rows: Vec<KeyColors>,
+    /// Colors of each class of keys, taking precedence over `rows`
+    pub classes: HashMap<KeyClass, KeyColors>,
+}
+
+impl Default for OverlayStyle {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            stroke_width: 1.0,
+            rounding: 0.0,
+            rows: Vec::new(),
+            classes: HashMap::new(),
+        }
+    }
+}
+
+impl OverlayStyle {
+    /// Fill and outline of `key` in `row` while pressed
+    #[must_use]
+    pub fn colors_for(
+        &self,
+        colors: &ThemeColors,
+        key: &VisualKeybdKeyHolder,
+        row: usize,
+    ) -> (Color32, Color32) {
+        let class = self.classes.get(&KeyClass::of(key.key));
+        let row = self.rows.get(row);
+        let pick = |f: fn(&KeyColors) -> Option<HexColor>, default: HexColor| {
+            class
+                .and_then(f)
+                .or_else(|| row.and_then(f))
+                .unwrap_or(default)
+                .0
+        };
+        (
+            pick(|c| c.key, colors.key),
+            pick(|c| c.outline, colors.outline),
+        )
+    }
+}
+
+/// Where the keyboard overlay sits on the art, in unscaled window pixels
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct KeyboardGeometry {
+    /// Left of the first key laid out
+    pub x: f32,
+    /// Top of the first row laid out
+    pub y: f32,
+    /// Tilt around the keyboard's center, in degrees
+    pub angle: f32,
+    /// Size of the keyboard, 1 being 10 pixels per key
+    pub scale: f32,
+}
+
+impl Default for KeyboardGeometry {
+    fn default() -> Self {
+        Self {
+            x: 4.0,
+            y: WINDOW_HEIGHT - 95.0,
+            angle: CAT_ANGLE.to_degrees(),
+            scale: 1.0,
+        }
+    }
+}
+
+/// Which side of the counter's anchor its text goes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CounterAlign {
+    /// Text starts at the anchor
+    Left,
+    Center,
+    /// Text ends at the anchor
+    #[default]
+    Right,
+}
+
+impl From<CounterAlign> for Align {
+    fn from(align: CounterAlign) -> Self {
+        match align {
+            CounterAlign::Left => Self::Min,
+            CounterAlign::Center => Self::Center,
+            CounterAlign::Right => Self::Max,
+        }
+    }
+}
+
+/// How the keystroke counter is drawn, in unscaled window pixels
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CounterStyle {
+    pub x: f32,
+    pub y: f32,
+    pub align: CounterAlign,
+    /// Tilt around the anchor, in degrees
+    pub angle: f32,
+    /// Font size
+    pub size: f32,
+    /// TTF or OTF font file, relative to the theme directory
+    pub font: Option<PathBuf>,
+    /// Color of an outline around the text
+    pub outline: Option<HexColor>,
+    pub outline_width: f32,
+    /// Color of a drop shadow under the text
+    pub shadow: Option<HexColor>,
+    /// How far the shadow is from the text, right and down
+    pub shadow_offset: [f32; 2],
+}
+
+impl Default for CounterStyle {
+    fn default() -> Self {
+        Self {
+            x: WINDOW_WIDTH / 2.0,
+            y: WINDOW_HEIGHT - (WINDOW_HEIGHT / 12.5) * 2.25,
+            align: CounterAlign::Right,
+            angle: CAT_ANGLE.to_degrees(),
+            size: WINDOW_HEIGHT / 12.5,
+            font: None,
+            outline: None,
+            outline_width: 1.0,
+            shadow: None,
+            shadow_offset: [1.5, 1.5],
+        }
+    }
+}
+
+/// Optional `theme.toml` in a theme directory, customizing how the theme is drawn
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct ThemeManifest {
+    pub colors: ThemeColors,
+    pub overlay: OverlayStyle,
+    pub keyboard: KeyboardGeometry,
+    pub counter: CounterStyle,
+}
+
+impl ThemeManifest {
+    /// Reads `dir`'s manifest, if it has one
+    fn load(dir: &Path) -> Option<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let text = std::fs::read_to_string(&path).ok()?;
+        let mut manifest: Self = toml::from_str(&text)
+            .inspect_err(|e| log::warn!("Ignoring invalid {}: {e}", path.display()))
+            .ok()?;
+        manifest.counter.font = manifest.counter.font.map(|font| dir.join(font));
+        Some(manifest)
+    }
+}
+
+/// Extensions tried, in order, for a theme's sound files
+pub const SOUND_EXTENSIONS: [&str; 4] = ["ogg", "wav", "flac", "mp3"];
+
+/// A theme's optional sounds, looked up in its `sounds` directory
+#[derive(Clone, Debug, Default)]
+pub struct SoundFiles {
+    /// `left.*`, played when the left paw slaps
+    pub left: Option<PathBuf>,
+    /// `right.*`, played when the right paw slaps
+    pub right: Option<PathBuf>,
+    /// `reaction.*`, played instead of the paw's sound for keys that make the "o" face
+    pub reaction: Option<PathBuf>,
+}
+
+impl SoundFiles {
+    fn find(dir: &Path) -> Self {
+        let find = |name: &str| {
+            SOUND_EXTENSIONS
+                .iter()
+                .map(|extension| dir.join(format!("{name}.{extension}")))
+                .find(|path| path.is_file())
+        };
+        Self {
+            left: find("left"),
+            right: find("right"),
+            reaction: find("reaction"),
+        }
+    }
+}
+
+#[derive(Default)]
 pub struct ThemeSet {
     pub themes: Vec<AppTheme>,
+    /// Every theme being loaded, in the order they're offered. Decoded themes are kept in this order
+    /// however long each takes.
+    pub ids: Vec<Arc<String>>,
     pub themes_loaded: Vec<AppThemeImage>,
     pub themes_rendered: Vec<AppThemeTexture>,
 }
 
-impl Default for ThemeSet {
-    fn default() -> Self {
-        ThemeSet {
-            themes: vec![],
-            themes_loaded: vec![
-                AppThemeImage {
-                    id: "standard".to_string().into(),
-                    paws_both: color_image_from_dynamic(
-                        image::load_from_memory_with_format(
-                            include_bytes!("../assets/frames/paws_both.png"),
-                            ImageFormat::Png,
-                        )
-                        .unwrap(),
-                    ),
-                    paws_left: color_image_from_dynamic(
-                        image::load_from_memory_with_format(
-                            include_bytes!("../assets/frames/paws_left.png"),
-                            ImageFormat::Png,
-                        )
-                        .unwrap(),
-                    ),
-                    paws_right: color_image_from_dynamic(
-                        image::load_from_memory_with_format(
-                            include_bytes!("../assets/frames/paws_right.png"),
-                            ImageFormat::Png,
-                        )
-                        .unwrap(),
-                    ),
-                    paws_up: color_image_from_dynamic(
-                        image::load_from_memory_with_format(
-                            include_bytes!("../assets/frames/paws_up.png"),
-                            ImageFormat::Png,
-                        )
-                        .unwrap(),
-                    ),
-                },
-                AppThemeImage {
-                    id: "standard-o".to_string().into(),
-                    paws_both: color_image_from_dynamic(
-                        image::load_from_memory_with_format(
-                            include_bytes!("../assets/frames/o/paws_both.png"),
-                            ImageFormat::Png,
-                        )
-                        .unwrap(),
-                    ),
-                    paws_left: color_image_from_dynamic(
-                        image::load_from_memory_with_format(
-                            include_bytes!("../assets/frames/o/paws_left.png"),
-                            ImageFormat::Png,
-                        )
-                        .unwrap(),
-                    ),
-                    paws_right: color_image_from_dynamic(
-                        image::load_from_memory_with_format(
-                            include_bytes!("../assets/frames/o/paws_right.png"),
-                            ImageFormat::Png,
-                        )
-                        .unwrap(),
-                    ),
-                    paws_up: color_image_from_dynamic(
-                        image::load_from_memory_with_format(
-                            include_bytes!("../assets/frames/o/paws_up.png"),
-                            ImageFormat::Png,
-                        )
-                        .unwrap(),
-                    ),
-                },
-            ],
-            themes_rendered: vec![],
+/// PNGs of the themes built into the app, with frames in `FRAME_FILES` order
+pub const EMBEDDED_THEMES: [(&str, [&[u8]; 4]); 2] = [
+    (
+        "standard",
+        [
+            include_bytes!("../assets/frames/paws_both.png"),
+            include_bytes!("../assets/frames/paws_left.png"),
+            include_bytes!("../assets/frames/paws_right.png"),
+            include_bytes!("../assets/frames/paws_up.png"),
+        ],
+    ),
+    (
+        "standard-o",
+        [
+            include_bytes!("../assets/frames/o/paws_both.png"),
+            include_bytes!("../assets/frames/o/paws_left.png"),
+            include_bytes!("../assets/frames/o/paws_right.png"),
+            include_bytes!("../assets/frames/o/paws_up.png"),
+        ],
+    ),
+];
+
+impl ThemeSet {
+    /// Adds a decoded theme, whose textures are made on the next `upload`
+    pub fn insert(&mut self, image: AppThemeImage) {
+        let rank = |id: &Arc<String>| {
+            self.ids
+                .iter()
+                .position(|other| other == id)
+                .unwrap_or(usize::MAX)
+        };
+        let at = self
+            .themes_loaded
+            .iter()
+            .position(|t| rank(&t.id) > rank(&image.id))
+            .unwrap_or(self.themes_loaded.len());
+        self.themes_rendered.insert(
+            at,
+            AppThemeTexture {
+                id: image.id.clone(),
+                paws_both: None,
+                paws_left: None,
+                paws_right: None,
+                paws_up: None,
+                idle: IdleFrames::default(),
+                background: None,
+                manifest: image.manifest.clone(),
+            },
+        );
+        self.themes_loaded.insert(at, image);
+    }
+
+    /// Stops offering theme `id`, which could not be decoded
+    pub fn forget(&mut self, id: &str) {
+        self.ids.retain(|other| other.as_str() != id);
+    }
+
+    /// Whether any theme is still waiting for its textures
+    #[must_use]
+    pub fn needs_upload(&self) -> bool {
+        self.themes_rendered.iter().any(|t| t.paws_up.is_none())
+    }
+
+    /// Makes textures for every theme that doesn't have them yet
+    pub fn upload(&mut self, ctx: &Context) {
+        for theme in self.themes_rendered.iter_mut() {
+            if theme.paws_up.is_some() {
+                continue;
+            }
+            let Some(image) = self
+                .themes_loaded
+                .iter()
+                .find(|t| Arc::ptr_eq(&t.id, &theme.id))
+            else {
+                continue;
+            };
+
+            let id = theme.id.clone();
+            let load = |image: &ColorImage, name: &str| {
+                load_texture_from_color_image(ctx, image, format!("{name}_{id}"))
+            };
+            theme.paws_both = Some(load(&image.paws_both, "paws_both"));
+            theme.paws_left = Some(load(&image.paws_left, "paws_left"));
+            theme.paws_right = Some(load(&image.paws_right, "paws_right"));
+            theme.idle = image.idle.map(|name, frame| load(frame, name));
+            theme.background = image
+                .background
+                .as_ref()
+                .map(|background| load(background, "background"));
+            theme.paws_up = Some(load(&image.paws_up, "paws_up"));
+        }
+    }
+
+    /// Ids of the themes that can be picked, loaded yet or not, leaving out "o" faces
+    #[must_use]
+    pub fn theme_ids(&self) -> Vec<Arc<String>> {
+        self.ids
+            .iter()
+            .filter(|id| !id.ends_with(O_FACE_SUFFIX))
+            .cloned()
+            .collect()
+    }
+
+    /// The theme after `current`, wrapping around
+    #[must_use]
+    pub fn next_theme_id(&self, current: &str) -> Option<Arc<String>> {
+        let ids = self.theme_ids();
+        let next = ids
+            .iter()
+            .position(|id| id.as_str() == current)
+            .map_or(0, |i| (i + 1) % ids.len());
+        ids.get(next).cloned()
+    }
+
+    /// The textures for theme `id`, or its "o" face if `o_face` is set and it has one.
+    /// Unknown ids and themes still loading fall back to the first theme that's ready.
+    #[must_use]
+    pub fn rendered(&self, id: &str, o_face: bool) -> Option<&AppThemeTexture> {
+        let mut ready = self.themes_rendered.iter().filter(|t| t.paws_up.is_some());
+        let find = |id: &str| {
+            self.themes_rendered
+                .iter()
+                .find(|t| t.id.as_str() == id && t.paws_up.is_some())
+        };
+
+        let base = find(id).or_else(|| ready.find(|t| !t.id.ends_with(O_FACE_SUFFIX)))?;
+
+        if o_face && let Some(theme) = find(&format!("{}{O_FACE_SUFFIX}", base.id)) {
+            Some(theme)
+        } else {
+            Some(base)
+        }
+    }
+}
+
+/// Where a theme's frames are decoded from
+enum ThemeSource {
+    Embedded(&'static str, [&'static [u8]; 4]),
+    Files(AppTheme),
+}
+
+impl ThemeSource {
+    fn id(&self) -> Arc<String> {
+        match self {
+            Self::Embedded(id, _) => id.to_string().into(),
+            Self::Files(theme) => theme.id.clone(),
         }
     }
+
+    fn decode(&self) -> Result<AppThemeImage, String> {
+        match self {
+            Self::Embedded(id, pngs) => AppThemeImage::embedded(id, *pngs),
+            Self::Files(theme) => AppThemeImage::load(theme),
+        }
+    }
+}
+
+/// Decodes the built-in themes and `themes` on a few threads, adding each to `set` as soon as it's
+/// done and repainting `ctx` so its textures get uploaded. Themes that fail are taken out of `set` and
+/// sent as `AppCommand::ForgetTheme` to `commands`. Threads stop early once `exit_notify` is set.
+pub fn load_in_background(
+    set: &Arc<RwLock<ThemeSet>>,
+    themes: &[AppTheme],
+    ctx: &Context,
+    commands: &Sender<AppCommand>,
+    exit_notify: &Arc<AtomicBool>,
+) -> Vec<JoinHandle<()>> {
+    let sources: VecDeque<ThemeSource> = EMBEDDED_THEMES
+        .iter()
+        .map(|(id, pngs)| ThemeSource::Embedded(id, *pngs))
+        .chain(themes.iter().cloned().map(ThemeSource::Files))
+        .collect();
+    {
+        let mut set = set.write();
+        set.ids = sources.iter().map(ThemeSource::id).collect();
+        set.themes = themes.to_vec();
+    }
+
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(sources.len());
+    let queue = Arc::new(Mutex::new(sources));
+    (0..workers)
+        .map(|_| {
+            let (set, ctx, commands, exit_notify, queue) = (
+                set.clone(),
+                ctx.clone(),
+                commands.clone(),
+                exit_notify.clone(),
+                queue.clone(),
+            );
+            thread::spawn(move || {
+                while !exit_notify.load(Ordering::Relaxed) {
+                    let Some(source) = queue.lock().pop_front() else {
+                        break;
+                    };
+                    match source.decode() {
+                        Ok(image) => {
+                            log::debug!("Decoded theme {}", image.id);
+                            set.write().insert(image);
+                        }
+                        Err(e) => {
+                            let id = source.id();
+                            log::warn!("Could not decode theme {id}, leaving it out: {e}");
+                            set.write().forget(&id);
+                            let _ = commands.send(AppCommand::ForgetTheme(id.to_string()));
+                        }
+                    }
+                    ctx.request_repaint();
+                }
+            })
+        })
+        .collect()
 }
 
 pub struct AppThemeTexture {
@@ -89,6 +585,9 @@ pub struct AppThemeTexture {
     pub paws_left: Option<TextureHandle>,
     pub paws_right: Option<TextureHandle>,
     pub paws_up: Option<TextureHandle>,
+    pub idle: IdleFrames<TextureHandle>,
+    pub background: Option<TextureHandle>,
+    pub manifest: ThemeManifest,
 }
 
 pub struct AppThemeImage {
@@ -97,14 +596,71 @@ pub struct AppThemeImage {
     pub paws_left: ColorImage,
     pub paws_right: ColorImage,
     pub paws_up: ColorImage,
+    pub idle: IdleFrames<ColorImage>,
+    pub background: Option<ColorImage>,
+    pub manifest: ThemeManifest,
 }
 
+impl AppThemeImage {
+    /// Decodes a theme built into the app, from `EMBEDDED_THEMES`
+    pub fn embedded(
+        id: &str,
+        [paws_both, paws_left, paws_right, paws_up]: [&[u8]; 4],
+    ) -> Result<Self, String> {
+        let decode = |png: &[u8]| {
+            image::load_from_memory_with_format(png, ImageFormat::Png)
+                .map(color_image_from_dynamic)
+                .map_err(|e| e.to_string())
+        };
+
+        Ok(Self {
+            id: id.to_string().into(),
+            paws_both: decode(paws_both)?,
+            paws_left: decode(paws_left)?,
+            paws_right: decode(paws_right)?,
+            paws_up: decode(paws_up)?,
+            idle: IdleFrames::default(),
+            background: None,
+            manifest: ThemeManifest::default(),
+        })
+    }
+
+    /// Decodes every frame of `theme`, failing with the first frame that can't be read
+    pub fn load(theme: &AppTheme) -> Result<Self, String> {
+        let decode = |path: &PathBuf| {
+            load_color_image_from_path(path).map_err(|e| format!("{}: {e}", path.display()))
+        };
+        let idle = &theme.idle;
+
+        Ok(Self {
+            id: theme.id.clone(),
+            paws_both: decode(&theme.paws_both)?,
+            paws_left: decode(&theme.paws_left)?,
+            paws_right: decode(&theme.paws_right)?,
+            paws_up: decode(&theme.paws_up)?,
+            idle: IdleFrames {
+                blink: idle.blink.as_ref().map(decode).transpose()?,
+                yawn: idle.yawn.as_ref().map(decode).transpose()?,
+                sleep: idle.sleep.as_ref().map(decode).transpose()?,
+                wake: idle.wake.as_ref().map(decode).transpose()?,
+            },
+            background: theme.background.as_ref().map(decode).transpose()?,
+            manifest: theme.manifest.clone(),
+        })
+    }
+}
+
+#[derive(Clone)]
 pub struct AppTheme {
     pub id: Arc<String>,
     pub paws_both: PathBuf,
     pub paws_left: PathBuf,
     pub paws_right: PathBuf,
     pub paws_up: PathBuf,
+    pub idle: IdleFrames<PathBuf>,
+    pub background: Option<PathBuf>,
+    pub sounds: SoundFiles,
+    pub manifest: ThemeManifest,
 }
 
 impl AppTheme {
@@ -118,6 +674,169 @@ impl AppTheme {
             paws_left: format!("{path_display}/paws_left.png").into(),
             paws_right: format!("{path_display}/paws_right.png").into(),
             paws_up: format!("{path_display}/paws_up.png").into(),
+            idle: IdleFrames {
+                blink: Self::idle_frame(path, IdleFrame::Blink),
+                yawn: Self::idle_frame(path, IdleFrame::Yawn),
+                sleep: Self::idle_frame(path, IdleFrame::Sleep),
+                wake: Self::idle_frame(path, IdleFrame::Wake),
+            },
+            background: Some(path.join(BACKGROUND_FILE)).filter(|path| path.is_file()),
+            sounds: SoundFiles::find(&path.join("sounds")),
+            manifest: ThemeManifest::load(path).unwrap_or_default(),
+        }
+    }
+
+    fn idle_frame(path: &Path, frame: IdleFrame) -> Option<PathBuf> {
+        let file = IDLE_FRAME_FILES[frame as usize];
+        Some(path.join("idle").join(file)).filter(|path| path.is_file())
+    }
+
+    /// Finds every theme in `dir`: one per subdirectory holding all of `FRAME_FILES`, plus its "o"
+    /// face when the subdirectory has a complete `o` directory of its own
+    #[must_use]
+    pub fn discover<P: AsRef<Path>>(dir: P) -> Vec<Self> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return vec![];
+        };
+
+        let mut themes = vec![];
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !Self::is_theme_dir(&path) {
+                continue;
+            }
+
+            let id = entry.file_name().to_string_lossy().into_owned();
+            let theme = Self::new(id, &path);
+            let o_face = path.join("o");
+            if Self::is_theme_dir(&o_face) {
+                let mut o_theme = Self::new(format!("{}{O_FACE_SUFFIX}", theme.id), &o_face);
+                // The "o" face is drawn the same way unless it says otherwise
+                o_theme.manifest =
+                    ThemeManifest::load(&o_face).unwrap_or_else(|| theme.manifest.clone());
+                o_theme.background = o_theme.background.or_else(|| theme.background.clone());
+                themes.push(o_theme);
+            }
+            themes.push(theme);
         }
+
+        themes.sort_by(|a, b| a.id.cmp(&b.id));
+        themes
+    }
+
+    fn is_theme_dir(path: &Path) -> bool {
+        FRAME_FILES.iter().all(|file| path.join(file).is_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(hex: &str) -> Result<Color32, String> {
+        HexColor::try_from(hex.to_string()).map(|color| color.0)
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse("#ff8000"), Ok(Color32::from_rgb(0xff, 0x80, 0x00)));
+        assert_eq!(parse("FF8000"), Ok(Color32::from_rgb(0xff, 0x80, 0x00)));
+        assert_eq!(
+            parse("#ff800080"),
+            Ok(Color32::from_rgba_unmultiplied(0xff, 0x80, 0x00, 0x80))
+        );
+    }
+
+    #[test]
+    fn rejects_other_colors() {
+        for hex in [
+            "",
+            "#",
+            "#fff",
+            "#ff80",
+            "#ff8000f",
+            "#gg8000",
+            "red",
+            "#ff800080ff",
+        ] {
+            assert!(parse(hex).is_err(), "{hex} parsed");
+        }
+    }
+
+    #[test]
+    fn round_trips_through_strings() {
+        for hex in ["#ff8000ff", "#00000000", "#123456ff"] {
+            let color = HexColor::try_from(hex.to_string()).unwrap();
+            assert_eq!(String::from(color), hex);
+        }
+    }
+
+    fn image(id: &str) -> AppThemeImage {
+        AppThemeImage {
+            id: Arc::new(id.to_string()),
+            paws_both: ColorImage::default(),
+            paws_left: ColorImage::default(),
+            paws_right: ColorImage::default(),
+            paws_up: ColorImage::default(),
+            idle: IdleFrames::default(),
+            background: None,
+            manifest: ThemeManifest::default(),
+        }
+    }
+
+    fn loaded(set: &ThemeSet) -> Vec<&str> {
+        set.themes_loaded.iter().map(|t| t.id.as_str()).collect()
+    }
+
+    #[test]
+    fn keeps_themes_in_offered_order_whenever_they_finish() {
+        let mut set = ThemeSet {
+            ids: ["a", "b", "c", "d"]
+                .map(|id| Arc::new(id.to_string()))
+                .to_vec(),
+            ..ThemeSet::default()
+        };
+        for id in ["c", "a", "d", "b"] {
+            set.insert(image(id));
+        }
+        assert_eq!(loaded(&set), ["a", "b", "c", "d"]);
+        let rendered: Vec<&str> = set.themes_rendered.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(rendered, ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn forgets_themes_that_failed_to_decode() {
+        let mut set = ThemeSet {
+            ids: ["a", "b", "b-o"]
+                .map(|id| Arc::new(id.to_string()))
+                .to_vec(),
+            ..ThemeSet::default()
+        };
+        set.forget("b");
+        assert_eq!(set.theme_ids(), [Arc::new("a".to_string())]);
+
+        set.insert(image("b-o"));
+        set.insert(image("a"));
+        assert_eq!(loaded(&set), ["a", "b-o"]);
+    }
+
+    #[test]
+    fn reports_frames_that_cannot_be_read() {
+        let missing = PathBuf::from("/nonexistent/bongocat/paws_both.png");
+        let theme = AppTheme {
+            id: Arc::new("missing".to_string()),
+            paws_both: missing.clone(),
+            paws_left: missing.clone(),
+            paws_right: missing.clone(),
+            paws_up: missing.clone(),
+            idle: IdleFrames::default(),
+            background: None,
+            sounds: SoundFiles::default(),
+            manifest: ThemeManifest::default(),
+        };
+        let Err(error) = AppThemeImage::load(&theme) else {
+            panic!("loaded a theme without frames");
+        };
+        assert!(error.contains("paws_both.png"), "{error}");
     }
 }
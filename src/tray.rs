@@ -0,0 +1,169 @@
+//! System tray icon with a menu for controlling the app
+use crate::app::command::AppCommand;
+use crate::config::{APP_ID, Background};
+use egui::Context;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+#[cfg(target_os = "linux")]
+use std::thread::JoinHandle;
+use tray_icon::menu::{Menu, MenuEvent, MenuId, MenuItem, PredefinedMenuItem, Submenu};
+use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
+
+thread_local! {
+    /// The Theme submenu and its items, on the thread the icon lives on
+    static THEME_ITEMS: RefCell<Option<(Submenu, Vec<(Arc<String>, MenuItem)>)>> =
+        const { RefCell::new(None) };
+}
+
+/// Keeps the tray icon around, dropping it removes the icon
+pub struct Tray {
+    #[cfg(not(target_os = "linux"))]
+    _icon: TrayIcon,
+    /// GTK thread the icon lives on
+    #[cfg(target_os = "linux")]
+    thread: JoinHandle<()>,
+}
+
+impl Tray {
+    /// Takes theme `id` out of the Theme submenu
+    pub fn remove_theme(&self, id: &str) {
+        let id = id.to_string();
+        let remove = move || {
+            THEME_ITEMS.with_borrow_mut(|themes| {
+                if let Some((submenu, items)) = themes {
+                    items.retain(|(item_id, item)| {
+                        let keep = **item_id != id;
+                        if !keep {
+                            let _ = submenu.remove(item);
+                        }
+                        keep
+                    });
+                }
+            });
+        };
+        // Menus belong to the GTK thread on Linux, and to the UI thread this is called from elsewhere
+        #[cfg(target_os = "linux")]
+        gtk::glib::idle_add_once(remove);
+        #[cfg(not(target_os = "linux"))]
+        remove();
+    }
+
+    /// Removes the icon, stopping its GTK thread on Linux
+    pub fn quit(self) {
+        #[cfg(target_os = "linux")]
+        {
+            gtk::glib::idle_add_once(gtk::main_quit);
+            if self.thread.join().is_err() {
+                log::warn!("Tray icon thread panicked");
+            }
+        }
+    }
+}
+
+fn icon() -> Icon {
+    let image = image::load_from_memory(include_bytes!("../assets/icon-256.png"))
+        .expect("Failed to load icon")
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+    Icon::from_rgba(image.into_raw(), width, height).expect("Failed to load icon")
+}
+
+fn build(
+    theme_ids: &[Arc<String>],
+    commands: Sender<AppCommand>,
+    ctx: Context,
+) -> Option<TrayIcon> {
+    let mut actions: HashMap<MenuId, AppCommand> = HashMap::new();
+    let mut item = |text: &str, command: AppCommand| {
+        let item = MenuItem::new(text, true, None);
+        actions.insert(item.id().clone(), command);
+        item
+    };
+
+    let show_hide = item("Show/hide", AppCommand::ToggleVisibility);
+    let pause = item("Pause/resume counting", AppCommand::TogglePause);
+    let stats = item("Stats", AppCommand::OpenStats);
+    let settings = item("Settings", AppCommand::OpenSettings);
+    let quit = item("Quit", AppCommand::Quit);
+
+    let themes = Submenu::new("Theme", true);
+    let mut theme_items = vec![];
+    for id in theme_ids {
+        let theme_item = item(id, AppCommand::SetTheme(id.to_string()));
+        let _ = themes.append(&theme_item);
+        theme_items.push((id.clone(), theme_item));
+    }
+
+    let backgrounds = Submenu::new("Background", true);
+    for background in Background::ALL {
+        let _ = backgrounds.append(&item(
+            background.name(),
+            AppCommand::SetBackground(background),
+        ));
+    }
+
+    let menu = Menu::new();
+    let _ = menu.append_items(&[
+        &show_hide,
+        &themes,
+        &backgrounds,
+        &pause,
+        &PredefinedMenuItem::separator(),
+        &stats,
+        &settings,
+        &PredefinedMenuItem::separator(),
+        &quit,
+    ]);
+
+    THEME_ITEMS.set(Some((themes.clone(), theme_items)));
+
+    MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+        if let Some(command) = actions.get(event.id()) {
+            let _ = commands.send(command.clone());
+            ctx.request_repaint();
+        }
+    }));
+
+    match TrayIconBuilder::new()
+        .with_menu(Box::new(menu))
+        .with_tooltip(APP_ID)
+        .with_icon(icon())
+        .build()
+    {
+        Ok(icon) => Some(icon),
+        Err(e) => {
+            log::warn!("Could not create tray icon: {e}");
+            None
+        }
+    }
+}
+
+/// Creates the tray icon. On Linux it lives on its own GTK thread, which runs until `Tray::quit` or
+/// stops right away when the icon can't be made.
+pub fn create(
+    theme_ids: Vec<Arc<String>>,
+    commands: Sender<AppCommand>,
+    ctx: Context,
+) -> Option<Tray> {
+    #[cfg(target_os = "linux")]
+    {
+        let thread = std::thread::spawn(move || {
+            if let Err(e) = gtk::init() {
+                log::warn!("Could not create tray icon: {e}");
+                return;
+            }
+            let Some(_icon) = build(&theme_ids, commands, ctx) else {
+                return;
+            };
+            gtk::main();
+        });
+        Some(Tray { thread })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        build(&theme_ids, commands, ctx).map(|icon| Tray { _icon: icon })
+    }
+}